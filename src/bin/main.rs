@@ -6,11 +6,12 @@ use druid::{
 };
 use flexi_logger::{Age, Cleanup, Criterion, FileSpec, Naming};
 
+use for_mqtt::data::common::CertFileTy;
 use for_mqtt::data::hierarchy::AppData;
 
 use for_mqtt::logic::deal_event;
 
-use for_mqtt::ui::ids::{SELF_SIGNED_FILE, TIPS};
+use for_mqtt::ui::ids::{CERT_FILE, EXPORT_FILE, REPLAY_FILE, TIPS};
 use for_mqtt::ui::{init_layout, tips};
 
 use backtrace::Backtrace;
@@ -21,6 +22,7 @@ use for_mqtt::data::AppEvent;
 use for_mqtt::ui::theme::{update_env, PAYLOAD_FONT_SIZE};
 use for_mqtt::util::custom_logger::CustomWriter;
 use for_mqtt::util::db::ArcDb;
+use for_mqtt::util::{cert, proto};
 use log::error;
 use log::LevelFilter::{Debug, Info};
 use std::process::exit;
@@ -69,6 +71,9 @@ fn main() -> Result<(), PlatformError> {
         exit(1);
     }));
 
+    proto::init(home_path.join("proto"));
+    cert::init(home_path.join("cert"));
+
     let mut config = Config::init(home_path.clone());
     if config.display_tips {
         config.display_tips = false;
@@ -123,17 +128,38 @@ impl AppDelegate<AppData> for Delegate {
         _env: &Env,
     ) -> Handled {
         // debug!("{:?}", data.get_self_signed_file());
-        if let Some(index) = cmd.get(SELF_SIGNED_FILE) {
-            data.set_self_signed_file(*index);
+        if let Some((index, ty)) = cmd.get(CERT_FILE) {
+            data.set_cert_file(*index, *ty);
+            return Handled::Yes;
+        } else if let Some(id) = cmd.get(REPLAY_FILE) {
+            data.set_replay_broker(*id);
             return Handled::Yes;
         } else if let Some(file_info) = cmd.get(commands::OPEN_FILE) {
+            if data.set_replay_file(Arc::new(file_info.path.to_string_lossy().to_string())) {
+                return Handled::Yes;
+            }
             // debug!("{} {:?}", data.brokers.len(), file_info,);
-            if let Some(index) = data.get_self_signed_file() {
+            if let Some((index, ty)) = data.get_cert_file() {
                 if let Some(broker) = data.brokers.get_mut(index) {
-                    broker.self_signed_ca = Arc::new(file_info.path.to_string_lossy().to_string());
+                    let path = Arc::new(file_info.path.to_string_lossy().to_string());
+                    match ty {
+                        CertFileTy::SelfSignedCa => broker.self_signed_ca = path,
+                        CertFileTy::ClientCert => broker.client_cert.cert = path,
+                        CertFileTy::ClientKey => broker.client_cert.key = path,
+                        CertFileTy::Pkcs12 => broker.client_cert.pkcs12 = path,
+                    }
                     return Handled::Yes;
                 }
             }
+        } else if let Some((id, ty)) = cmd.get(EXPORT_FILE) {
+            data.set_export_broker(*id, *ty);
+            return Handled::Yes;
+        } else if let Some(file_info) = cmd.get(commands::SAVE_FILE_AS) {
+            if let Err(e) = data.export(file_info.path()) {
+                error!("{:?}", e);
+                data.hint = Arc::new(format!("fail to export: {}", e));
+            }
+            return Handled::Yes;
         } else if let Some(_) = cmd.get(TIPS) {
             let new_win = WindowDesc::new(tips::tips_ui_builder(self.0.clone()))
                 .window_size((500.0, 240.0))
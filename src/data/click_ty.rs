@@ -1,4 +1,5 @@
 use crate::data::common::SubscribeHis;
+use crate::data::AString;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ClickTy {
@@ -6,4 +7,5 @@ pub enum ClickTy {
     SubscribeTopic(usize, u32),
     SubscribeHis(SubscribeHis),
     ConnectTab(usize),
+    TopicNode(usize, AString),
 }
@@ -1,22 +1,33 @@
 mod impls;
 
-use crate::data::db::BrokerDB;
+use crate::data::db::{BrokerDB, MsgKey};
 use crate::data::hierarchy::UnsubcribeTracing;
 use crate::data::{AString, AppEvent};
-use crate::util::consts::{TY_HEX, TY_JSON, TY_TEXT};
-use anyhow::bail;
+use crate::mqtt::topic::is_valid_topic;
+use crate::util::consts::{
+    MAX_REPLAY_SPEED, MIN_REPLAY_SPEED, TY_BASE64, TY_CBOR, TY_GZIP, TY_HEX, TY_JSON, TY_MSGPACK,
+    TY_PROTOBUF, TY_TEXT, TY_ZSTD,
+};
+use anyhow::{anyhow, bail};
 use bytes::Bytes;
 use crossbeam_channel::Sender;
 use druid::im::Vector;
 use druid::{Data, Lens};
 use log::{debug, error};
 use pretty_hex::simple_hex;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::util::general_id;
+use crate::mqtt::benchmark::{BenchConfig, BenchReport};
+use crate::util::latency::bucket_counts;
+use crate::util::replay::ReplayConfig;
+use crate::util::{codec, compress, general_id, now_timestamp, proto};
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 static U32: AtomicU32 = AtomicU32::new(0);
 
@@ -48,6 +59,11 @@ pub struct SubscribeTopic {
     pub qos: QoS,
     pub status: SubscribeStatus,
     pub payload_ty: PayloadTy,
+    /// protobuf的message类型
+    #[data(ignore)]
+    pub proto_msg: AString,
+    /// 订阅失败的原因，如 0x87 not authorized
+    pub fail_reason: AString,
 }
 #[derive(Debug, Clone, Eq, Lens, Deserialize, Serialize, Data)]
 pub struct SubscribeHis {
@@ -59,6 +75,8 @@ pub struct SubscribeHis {
     pub(crate) topic: AString,
     pub(crate) qos: QoS,
     pub payload_ty: PayloadTy,
+    #[serde(default)]
+    pub proto_msg: AString,
 }
 
 #[derive(Debug, Data, Clone, Eq, PartialEq)]
@@ -95,12 +113,33 @@ pub struct PublicMsg {
     pub qos: AString,
     pub status: PublicStatus,
     pub payload_ty: AString,
+    pub retain: bool,
     pub time: AString,
+    pub timestamp: i64,
+    pub properties: MsgProperties,
+    pub show_properties: bool,
+    /// payload的长度，如 12B、12B/40B(压缩/解压后)
+    pub size: AString,
+    /// 原始的payload，用于以其他格式查看
+    pub payload: Arc<Bytes>,
+    pub show_inspector: bool,
+    pub inspect_ty: InspectTy,
+    /// 发布的时间，用于计算ack的延迟
+    #[data(ignore)]
+    pub sent_at: Option<Instant>,
+    /// 发布到收到ack的延迟，如 12.3ms
+    pub latency: AString,
+    /// 在sled中的key，用于向前翻页
+    pub db_key: Option<MsgKey>,
 }
 #[derive(Debug, Data, Clone, Eq, PartialEq)]
 pub enum PublicStatus {
     Ing,
     Success,
+    /// 发布失败的原因
+    Failed(AString),
+    /// 超时未收到ack
+    TimedOut,
 }
 
 #[derive(Debug, Data, Clone, Eq, PartialEq, Lens)]
@@ -111,6 +150,7 @@ pub struct PublicInput {
     pub qos: QoS,
     pub retain: bool,
     pub payload_ty: PayloadTy,
+    pub proto_msg: AString,
 }
 
 #[derive(Data, Clone, Debug, Eq, PartialEq)]
@@ -119,7 +159,33 @@ pub struct SubscribeMsg {
     pub msg: AString,
     pub qos: AString,
     pub payload_ty: AString,
+    pub retain: bool,
     pub time: AString,
+    pub timestamp: i64,
+    pub properties: MsgProperties,
+    pub show_properties: bool,
+    /// payload的长度，如 12B、12B/40B(压缩/解压后)
+    pub size: AString,
+    /// 原始的payload，用于以其他格式查看
+    pub payload: Arc<Bytes>,
+    pub show_inspector: bool,
+    pub inspect_ty: InspectTy,
+    /// 在sled中的key，用于向前翻页
+    pub db_key: Option<MsgKey>,
+}
+
+/// v5的publish properties
+#[derive(Data, Debug, Clone, Default, Eq, PartialEq, Lens, Deserialize, Serialize)]
+pub struct MsgProperties {
+    /// payload是否为utf8
+    pub payload_format_indicator: bool,
+    /// seconds, 0表示不设置
+    pub message_expiry_interval: u32,
+    pub content_type: AString,
+    pub response_topic: AString,
+    pub correlation_data: AString,
+    /// 每行一个 key=value
+    pub user_properties: AString,
 }
 
 #[derive(Data, Debug, Clone, Eq, PartialEq, Lens)]
@@ -128,6 +194,7 @@ pub struct SubscribeInput {
     pub(crate) topic: AString,
     pub(crate) qos: QoS,
     pub(crate) payload_ty: PayloadTy,
+    pub(crate) proto_msg: AString,
 }
 
 impl SubscribeInput {
@@ -137,6 +204,7 @@ impl SubscribeInput {
             topic: Arc::new("".to_string()),
             qos: Default::default(),
             payload_ty: Default::default(),
+            proto_msg: Default::default(),
         }
     }
 }
@@ -153,6 +221,8 @@ pub struct TabStatus {
     pub(crate) id: usize,
     pub(crate) try_connect: bool,
     pub(crate) connected: bool,
+    /// 最近一次连接失败的原因
+    pub(crate) err: AString,
 }
 
 #[derive(Data, Clone, Copy, Eq, PartialEq, Debug, Hash)]
@@ -176,7 +246,7 @@ pub struct Broker {
     pub name: AString,
     pub addr: AString,
     pub port: Option<u16>,
-    pub params: AString,
+    pub option: ConnectOption,
     pub use_credentials: bool,
     pub auto_connect: bool,
     pub user_name: AString,
@@ -192,6 +262,7 @@ pub struct Broker {
     pub tls: bool,
     pub signed_ty: SignedTy,
     pub self_signed_ca: AString,
+    pub client_cert: ClientCert,
 
     pub subscribe_hises: Vector<SubscribeHis>,
     pub subscribe_topics: Vector<SubscribeTopic>,
@@ -200,10 +271,418 @@ pub struct Broker {
     pub public_input: PublicInput,
     pub unsubscribe_ing: Vector<UnsubcribeTracing>,
     pub tab_status: TabStatus,
+    pub retention: MsgRetention,
+    /// 因超出保留策略而被移除的消息数
+    pub evicted: usize,
+    /// msgs前面加载的历史消息数，这些消息不参与淘汰，但总数有上限
+    pub history_len: usize,
+    pub msg_filter: MsgFilter,
+    #[data(ignore)]
+    #[lens(ignore)]
+    pub filtered_cache: Arc<Mutex<FilteredMsgs>>,
+    /// 暂停时收发的消息暂存在paused_msgs，恢复后按顺序加入msgs
+    pub paused: bool,
+    pub paused_msgs: Vector<Msg>,
+    /// msgs中实时消息原始payload的总字节数，用于按大小淘汰
+    #[data(ignore)]
+    #[lens(ignore)]
+    pub msgs_bytes: usize,
+    /// 收到过的topic层级，按深度优先顺序平铺
+    pub topic_tree: Vector<TopicNode>,
+    pub replay: ReplayOption,
+    pub schedule: ScheduleOption,
+    pub benchmark: BenchmarkOption,
+    pub templates: Vector<PublishTemplate>,
+    /// 保存模板时使用的名称
+    pub template_name: AString,
+    pub latency: LatencyHistogram,
+    /// 发布计数，用于模板变量{{counter}}
+    #[data(ignore)]
+    pub publish_counter: u64,
+    /// 每次发起连接或断开时递增，用于忽略旧连接的connack超时等事件
+    #[data(ignore)]
+    pub conn_generation: u64,
+}
+
+/// 最近的发布ack延迟(微秒)，超过容量时丢弃最早的
+#[derive(Debug, Clone, Data, Default)]
+pub struct LatencyHistogram {
+    pub samples: Vector<u64>,
+}
+
+impl LatencyHistogram {
+    const CAPACITY: usize = 1000;
+
+    pub fn record(&mut self, latency: Duration) {
+        if self.samples.len() >= Self::CAPACITY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency.as_micros() as u64);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.samples.is_empty() {
+            return "ack latency: -".to_string();
+        }
+        let buckets = bucket_counts(self.samples.iter())
+            .into_iter()
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect::<Vec<String>>()
+            .join("  ");
+        format!("ack latency({}): {}", self.samples.len(), buckets)
+    }
+}
+
+/// 保存的发布模板，topic和payload中可以使用变量
+#[derive(Debug, Clone, Data, Lens, Eq, PartialEq, Deserialize, Serialize)]
+pub struct PublishTemplate {
+    pub name: AString,
+    pub topic: AString,
+    pub msg: AString,
+    pub payload_ty: PayloadTy,
+    #[serde(default)]
+    pub proto_msg: AString,
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+/// 按固定间隔发布开始时的发布输入，每次发布都会替换模板变量
+#[derive(Debug, Clone, Data, Lens)]
+pub struct ScheduleOption {
+    /// 毫秒
+    pub interval: AString,
+    /// 发布次数，为空或0时不限制
+    pub count: AString,
+    pub running: bool,
+    pub sent: usize,
+    pub acked: usize,
+    /// 已发布、等待ack的trace_id
+    #[data(ignore)]
+    pub trace_ids: HashSet<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduleConfig {
+    pub interval: Duration,
+    pub count: Option<u64>,
+    /// 开始时的发布输入，之后修改输入不影响定时发布
+    pub input: PublicInput,
+}
+
+impl Default for ScheduleOption {
+    fn default() -> Self {
+        Self {
+            interval: Arc::new("1000".to_string()),
+            count: Default::default(),
+            running: false,
+            sent: 0,
+            acked: 0,
+            trace_ids: Default::default(),
+        }
+    }
+}
+
+impl ScheduleOption {
+    pub fn to_config(&self, input: &PublicInput) -> anyhow::Result<ScheduleConfig> {
+        let Ok(interval) = self.interval.trim().parse::<u64>() else {
+            bail!("invalid interval: {}", self.interval);
+        };
+        if interval == 0 {
+            bail!("interval must be greater than 0");
+        }
+        let count = if self.count.trim().is_empty() {
+            None
+        } else {
+            let Ok(count) = self.count.trim().parse::<u64>() else {
+                bail!("invalid count: {}", self.count);
+            };
+            (count > 0).then_some(count)
+        };
+        Ok(ScheduleConfig {
+            interval: Duration::from_millis(interval),
+            count,
+            input: input.clone(),
+        })
+    }
+
+    pub fn start(&mut self) {
+        self.running = true;
+        self.sent = 0;
+        self.acked = 0;
+        self.trace_ids.clear();
+    }
+}
+
+/// 压测的设置与结果
+#[derive(Debug, Clone, Data, Lens)]
+pub struct BenchmarkOption {
+    pub show: bool,
+    pub clients: AString,
+    /// 每个连接每秒发布的消息数
+    pub rate: AString,
+    pub payload_size: AString,
+    pub qos: QoS,
+    /// 秒
+    pub duration: AString,
+    pub topic: AString,
+    pub running: bool,
+    pub summary: AString,
+    #[data(ignore)]
+    pub report: Option<Arc<BenchReport>>,
+}
+
+impl Default for BenchmarkOption {
+    fn default() -> Self {
+        Self {
+            show: false,
+            clients: Arc::new("10".to_string()),
+            rate: Arc::new("10".to_string()),
+            payload_size: Arc::new("64".to_string()),
+            qos: QoS::AtMostOnce,
+            duration: Arc::new("10".to_string()),
+            topic: Arc::new("for-mqtt/bench".to_string()),
+            running: false,
+            summary: Default::default(),
+            report: None,
+        }
+    }
+}
+
+impl BenchmarkOption {
+    const MAX_CLIENTS: u64 = 1000;
+    /// rate的范围，保证发布间隔不为0且不溢出
+    const MIN_RATE: f64 = 0.001;
+    const MAX_RATE: f64 = 100_000.0;
+    const MAX_PAYLOAD_SIZE: u64 = 1024 * 1024;
+
+    pub fn to_config(&self) -> anyhow::Result<BenchConfig> {
+        let parse = |name: &str, val: &AString| -> anyhow::Result<u64> {
+            val.trim()
+                .parse::<u64>()
+                .map_err(|_| anyhow!("invalid {}: {}", name, val))
+        };
+        let clients = parse("clients", &self.clients)?;
+        let duration = parse("duration", &self.duration)?;
+        let Ok(rate) = self.rate.trim().parse::<f64>() else {
+            bail!("invalid rate: {}", self.rate);
+        };
+        if clients == 0 || duration == 0 {
+            bail!("clients and duration must be greater than 0");
+        } else if clients > Self::MAX_CLIENTS {
+            bail!("clients must not exceed {}", Self::MAX_CLIENTS);
+        } else if !rate.is_finite() || !(Self::MIN_RATE..=Self::MAX_RATE).contains(&rate) {
+            bail!(
+                "rate must be between {} and {}",
+                Self::MIN_RATE,
+                Self::MAX_RATE
+            );
+        }
+        let payload_size = parse("payload size", &self.payload_size)?;
+        if payload_size > Self::MAX_PAYLOAD_SIZE {
+            bail!("payload size must not exceed {}", Self::MAX_PAYLOAD_SIZE);
+        }
+        let topic = self.topic.trim().trim_end_matches('/');
+        if !is_valid_topic(topic) {
+            bail!("invalid topic: {}", self.topic);
+        }
+        Ok(BenchConfig {
+            clients: clients as usize,
+            rate,
+            payload_size: payload_size as usize,
+            qos: self.qos.clone(),
+            duration: Duration::from_secs(duration),
+            topic: topic.to_string(),
+        })
+    }
+}
+
+/// 回放json lines记录的设置
+#[derive(Debug, Clone, Data, Lens)]
+pub struct ReplayOption {
+    pub show: bool,
+    pub file: AString,
+    pub speed: ReplaySpeed,
+    /// 倍速，只对ReplaySpeed::Multiplier生效
+    pub multiplier: AString,
+    /// topic前缀替换，prefix_from为空时不替换
+    pub prefix_from: AString,
+    pub prefix_to: AString,
+    pub looped: bool,
+    pub running: bool,
+}
+
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum ReplaySpeed {
+    /// 按原始的时间间隔
+    Original,
+    Multiplier,
+    /// 不等待，尽快发送
+    AsFast,
+}
+
+impl Default for ReplayOption {
+    fn default() -> Self {
+        Self {
+            show: false,
+            file: Default::default(),
+            speed: Default::default(),
+            multiplier: Arc::new("1".to_string()),
+            prefix_from: Default::default(),
+            prefix_to: Default::default(),
+            looped: false,
+            running: false,
+        }
+    }
+}
+
+impl Default for ReplaySpeed {
+    fn default() -> Self {
+        Self::Original
+    }
+}
+
+/// topic树的节点，path为从根节点开始的完整topic
+#[derive(Debug, Clone, Data, Lens)]
+pub struct TopicNode {
+    pub broker_id: usize,
+    pub path: AString,
+    pub name: AString,
+    pub depth: usize,
+    pub expanded: bool,
+    pub has_children: bool,
+    /// 该topic本身是否收到过消息，中间层级可能没有
+    pub has_msg: bool,
+    pub count: usize,
+    pub last_payload: AString,
+    pub last_time: AString,
+    pub retained: bool,
+}
+
+/// 消息列表的保留策略，0表示不限制
+#[derive(Debug, Clone, Data, Lens, Eq, PartialEq, Deserialize, Serialize)]
+pub struct MsgRetention {
+    pub max_count: usize,
+    pub max_bytes: usize,
+    /// seconds
+    pub max_age: u64,
+}
+
+/// 双向认证的客户端证书
+#[derive(Debug, Clone, Default, Data, Lens, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ClientCert {
+    pub enable: bool,
+    #[serde(default)]
+    pub ty: ClientCertTy,
+    /// pem格式的证书链
+    pub cert: AString,
+    /// pem格式的私钥
+    pub key: AString,
+    #[serde(default)]
+    pub key_ty: ClientKeyTy,
+    #[serde(default)]
+    pub pkcs12: AString,
+    #[serde(default)]
+    pub pkcs12_password: AString,
+}
+
+/// 消息列表的过滤条件，点击过滤后生效
+#[derive(Debug, Clone, Default, Data, Lens)]
+pub struct MsgFilter {
+    /// topic filter，支持通配符
+    pub topic: AString,
+    pub payload: AString,
+    /// payload是否按正则匹配
+    pub regex: bool,
+    pub direction: MsgDirection,
+    pub qos: Option<QoS>,
+    /// %Y-%m-%d %H:%M:%S
+    pub start: AString,
+    pub end: AString,
+    pub err: AString,
+    /// 生效中的过滤条件，为None时显示全部消息
+    pub active: Option<Arc<ActiveFilter>>,
+}
+
+#[derive(Debug)]
+pub struct ActiveFilter {
+    pub topic: Option<String>,
+    pub payload: Option<PayloadMatcher>,
+    pub direction: MsgDirection,
+    pub qos: Option<AString>,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+/// 过滤结果的缓存，msgs或生效的过滤条件变化时才重新计算
+#[derive(Debug, Default)]
+pub struct FilteredMsgs {
+    msgs: Vector<Msg>,
+    filter: Option<Arc<ActiveFilter>>,
+    indexes: Arc<Vec<usize>>,
+    filtered: Vector<Msg>,
+}
+
+#[derive(Debug)]
+pub enum PayloadMatcher {
+    Contains(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone, Copy, Data, PartialEq, Eq)]
+pub enum MsgDirection {
+    All,
+    Public,
+    Subscribe,
+}
+
+impl Default for MsgDirection {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+/// 连接参数
+#[derive(Debug, Clone, Data, Lens, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ConnectOption {
+    /// seconds，至少为5
+    pub keep_alive: u16,
+    pub clean_session: bool,
+    pub max_incoming_packet_size: usize,
+    pub max_outgoing_packet_size: usize,
+    /// seconds
+    pub conn_timeout: u64,
 }
 
 impl Broker {
+    /// 返回符合过滤条件的消息及其在msgs中的下标
+    pub fn filtered_msgs(&self, filter: &Arc<ActiveFilter>) -> (Arc<Vec<usize>>, Vector<Msg>) {
+        let mut cache = self.filtered_cache.lock().unwrap();
+        let is_stale = !cache.msgs.same(&self.msgs)
+            || !cache
+                .filter
+                .as_ref()
+                .map_or(false, |x| Arc::ptr_eq(x, filter));
+        if is_stale {
+            let indexes: Vec<usize> = self
+                .msgs
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| filter.matches(x))
+                .map(|(index, _)| index)
+                .collect();
+            cache.filtered = indexes.iter().map(|x| self.msgs[*x].clone()).collect();
+            cache.indexes = Arc::new(indexes);
+            cache.msgs = self.msgs.clone();
+            cache.filter = Some(filter.clone());
+        }
+        (cache.indexes.clone(), cache.filtered.clone())
+    }
     pub fn init_connection(&mut self) -> anyhow::Result<()> {
+        self.conn_generation += 1;
         if self.client_id.as_str().is_empty() {
             self.client_id = general_id().into();
         }
@@ -212,6 +691,12 @@ impl Broker {
             bail!("addr not be empty");
         } else if self.port.is_none() {
             bail!("port not be empty");
+        } else if self.option.max_incoming_packet_size == 0
+            || self.option.max_outgoing_packet_size == 0
+        {
+            bail!("max packet size not be 0");
+        } else if self.option.conn_timeout == 0 {
+            bail!("connect timeout not be 0");
         } else if self.use_credentials {
             if self.user_name.is_empty() {
                 bail!("user name not be empty");
@@ -223,7 +708,24 @@ impl Broker {
                 bail!("self signed ca not be empty");
             }
         }
+        if self.tls && self.client_cert.enable {
+            match self.client_cert.ty {
+                ClientCertTy::Pem => {
+                    if self.client_cert.cert.is_empty() {
+                        bail!("client cert not be empty");
+                    } else if self.client_cert.key.is_empty() {
+                        bail!("client key not be empty");
+                    }
+                }
+                ClientCertTy::Pkcs12 => {
+                    if self.client_cert.pkcs12.is_empty() {
+                        bail!("pkcs12 file not be empty");
+                    }
+                }
+            }
+        }
         self.tab_status.try_connect = true;
+        self.tab_status.err = Default::default();
         self.stored = true;
         Ok(())
     }
@@ -235,19 +737,173 @@ impl Broker {
             name: self.name.clone(),
             addr: self.addr.clone(),
             port: self.port.clone(),
-            params: self.params.clone(),
+            params: Default::default(),
+            option: Some(self.option.clone()),
             use_credentials: self.use_credentials,
             user_name: self.user_name.clone(),
             password: self.password.clone(),
             tls: self.tls,
             signed_ty: self.signed_ty,
             self_signed_ca: self.self_signed_ca.clone(),
+            client_cert: self.client_cert.clone(),
             subscribe_hises: self.subscribe_hises.clone(),
             auto_connect: self.auto_connect.clone(),
+            retention: self.retention.clone(),
+            templates: self.templates.clone(),
+        }
+    }
+
+    /// 以当前的发布输入生成模板，同名的模板会被替换
+    pub fn save_template(&mut self) -> anyhow::Result<()> {
+        let name = Arc::new(self.template_name.trim().to_string());
+        if name.is_empty() {
+            bail!("template name is empty");
+        }
+        let template = PublishTemplate::from_input(name, &self.public_input);
+        if let Some(old) = self.templates.iter_mut().find(|x| x.name == template.name) {
+            *old = template;
+        } else {
+            self.templates.push_back(template);
+        }
+        Ok(())
+    }
+
+    pub fn replay_config(&self) -> anyhow::Result<ReplayConfig> {
+        let replay = &self.replay;
+        if replay.file.is_empty() {
+            bail!("replay file is empty");
+        }
+        let speed = match replay.speed {
+            ReplaySpeed::Original => Some(1.0),
+            ReplaySpeed::Multiplier => {
+                let Ok(speed) = replay.multiplier.trim().parse::<f64>() else {
+                    bail!("invalid replay speed: {}", replay.multiplier);
+                };
+                if !(MIN_REPLAY_SPEED..=MAX_REPLAY_SPEED).contains(&speed) {
+                    bail!(
+                        "replay speed must be between {} and {}",
+                        MIN_REPLAY_SPEED,
+                        MAX_REPLAY_SPEED
+                    );
+                }
+                Some(speed)
+            }
+            ReplaySpeed::AsFast => None,
+        };
+        Ok(ReplayConfig {
+            path: PathBuf::from(replay.file.as_str()),
+            speed,
+            prefix: (!replay.prefix_from.is_empty())
+                .then(|| (replay.prefix_from.to_string(), replay.prefix_to.to_string())),
+            looped: replay.looped,
+        })
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        let msgs = std::mem::take(&mut self.paused_msgs);
+        self.msgs_bytes += msgs.iter().map(|x| x.payload().len()).sum::<usize>();
+        self.msgs.append(msgs);
+        self.evict_msgs();
+    }
+
+    /// 更新topic树：缺失的层级按名称顺序插入，叶子节点记录最新的消息
+    pub fn record_topic(&mut self, topic: &str, payload: AString, time: AString, retain: bool) {
+        let levels: Vec<&str> = topic.split('/').collect();
+        let mut start = 0;
+        for depth in 0..levels.len() {
+            let name = levels[depth];
+            let mut index = start;
+            let mut found = false;
+            while let Some(node) = self.topic_tree.get(index) {
+                if node.depth < depth {
+                    break;
+                } else if node.depth == depth {
+                    match node.name.as_str().cmp(name) {
+                        std::cmp::Ordering::Equal => {
+                            found = true;
+                            break;
+                        }
+                        std::cmp::Ordering::Greater => break,
+                        std::cmp::Ordering::Less => {}
+                    }
+                }
+                index += 1;
+            }
+            if !found {
+                if depth > 0 {
+                    self.topic_tree[start - 1].has_children = true;
+                }
+                self.topic_tree.insert(
+                    index,
+                    TopicNode {
+                        broker_id: self.id,
+                        path: Arc::new(levels[..=depth].join("/")),
+                        name: Arc::new(name.to_string()),
+                        depth,
+                        expanded: false,
+                        has_children: false,
+                        has_msg: false,
+                        count: 0,
+                        last_payload: Default::default(),
+                        last_time: Default::default(),
+                        retained: false,
+                    },
+                );
+            }
+            start = index + 1;
+        }
+        if let Some(node) = self.topic_tree.get_mut(start - 1) {
+            node.has_msg = true;
+            node.count += 1;
+            node.last_payload = payload;
+            node.last_time = time;
+            node.retained = retain;
+        }
+    }
+
+    /// 暂停时消息先暂存，保证恢复后的顺序
+    pub fn push_msg(&mut self, msg: Msg) {
+        if self.paused {
+            self.paused_msgs.push_back(msg);
+            return;
+        }
+        self.msgs_bytes += msg.payload().len();
+        self.msgs.push_back(msg);
+        self.evict_msgs();
+    }
+
+    /// 按保留策略从最早的实时消息开始移除，至少保留最新的一条；加载的历史消息不移除
+    fn evict_msgs(&mut self) {
+        let now = now_timestamp();
+        let start = self.history_len;
+        let len = self.msgs.len().saturating_sub(start);
+        let mut count = 0;
+        while len - count > 1 {
+            let front = &self.msgs[start + count];
+            if !self
+                .retention
+                .is_exceeded(len - count, self.msgs_bytes, now - front.timestamp())
+            {
+                break;
+            }
+            self.msgs_bytes -= front.payload().len();
+            count += 1;
+        }
+        if count > 0 {
+            let rest = self.msgs.split_off(start + count);
+            self.msgs.truncate(start);
+            self.msgs.append(rest);
+            self.evicted += count;
         }
     }
 
     pub fn disconnect(&mut self, clear: bool) {
+        self.conn_generation += 1;
         self.tab_status.try_connect = false;
         self.tab_status.connected = false;
         if !self.auto_connect {
@@ -255,6 +911,12 @@ impl Broker {
         }
         if clear {
             self.msgs.clear();
+            self.msgs_bytes = 0;
+            self.history_len = 0;
+            self.paused_msgs.clear();
+            self.topic_tree.clear();
+            self.latency.clear();
+            self.evicted = 0;
         }
         self.unsubscribe_ing.clear();
     }
@@ -266,6 +928,37 @@ impl PartialEq for SubscribeHis {
             && self.topic == other.topic
             && self.qos == other.qos
             && self.payload_ty == other.payload_ty
+            && self.proto_msg == other.proto_msg
+    }
+}
+
+impl Default for ConnectOption {
+    fn default() -> Self {
+        Self {
+            keep_alive: 60,
+            clean_session: true,
+            max_incoming_packet_size: 10 * 1024,
+            max_outgoing_packet_size: 10 * 1024,
+            conn_timeout: 5,
+        }
+    }
+}
+
+impl MsgRetention {
+    pub fn is_exceeded(&self, count: usize, bytes: usize, age_millis: i64) -> bool {
+        (self.max_count > 0 && count > self.max_count)
+            || (self.max_bytes > 0 && bytes > self.max_bytes)
+            || (self.max_age > 0 && age_millis > (self.max_age * 1000) as i64)
+    }
+}
+
+impl Default for MsgRetention {
+    fn default() -> Self {
+        Self {
+            max_count: 50,
+            max_bytes: 0,
+            max_age: 0,
+        }
     }
 }
 
@@ -293,11 +986,21 @@ impl QoS {
     }
 }
 #[derive(Data, Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
-/// 消息的格式：普通字符串、json字符串、hex
+/// 消息的格式：普通字符串、json字符串、hex、protobuf、cbor、msgpack
 pub enum PayloadTy {
     Text,
     Json,
     Hex,
+    /// 以json显示、输入，需指定message类型
+    Protobuf,
+    /// 以json显示、输入
+    Cbor,
+    /// 以json显示、输入
+    MsgPack,
+    /// base64编码的数据，解码后若为gzip、zstd则继续解压
+    Base64,
+    Gzip,
+    Zstd,
 }
 
 impl PayloadTy {
@@ -306,21 +1009,25 @@ impl PayloadTy {
             PayloadTy::Text => TY_TEXT.clone(),
             PayloadTy::Json => TY_JSON.clone(),
             PayloadTy::Hex => TY_HEX.clone(),
+            PayloadTy::Protobuf => TY_PROTOBUF.clone(),
+            PayloadTy::Cbor => TY_CBOR.clone(),
+            PayloadTy::MsgPack => TY_MSGPACK.clone(),
+            PayloadTy::Base64 => TY_BASE64.clone(),
+            PayloadTy::Gzip => TY_GZIP.clone(),
+            PayloadTy::Zstd => TY_ZSTD.clone(),
         }
     }
-    pub fn format(&self, data: Arc<Bytes>) -> String {
-        match self {
+    /// 返回payload的显示内容及长度，base64、压缩的类型只解码一次，同时显示解码后的长度
+    /// proto_msg: protobuf的message类型，只对Protobuf生效
+    pub fn format(&self, data: Arc<Bytes>, proto_msg: &str) -> (String, AString) {
+        let mut decoded_len = None;
+        let text = match self {
             PayloadTy::Text => String::from_utf8_lossy(data.as_ref()).to_string(),
             PayloadTy::Json => match String::from_utf8(data.to_vec()) {
-                Ok(rs) => {
-                    let Ok(json) = serde_json::from_str::<Value>(rs.as_str()) else {
-                        return rs;
-                    };
-                    let Ok(json) = serde_json::to_string_pretty(&json) else {
-                        return rs;
-                    };
-                    json
-                }
+                Ok(rs) => serde_json::from_str::<Value>(rs.as_str())
+                    .ok()
+                    .and_then(|json| serde_json::to_string_pretty(&json).ok())
+                    .unwrap_or(rs),
                 Err(err) => {
                     error!("{}", err.to_string());
                     let rs = String::from_utf8_lossy(data.as_ref()).to_string();
@@ -328,10 +1035,63 @@ impl PayloadTy {
                 }
             },
             PayloadTy::Hex => simple_hex(data.as_ref()),
-        }
+            PayloadTy::Protobuf => match proto::decode_to_json(proto_msg, data.as_ref()) {
+                Ok(json) => json,
+                Err(err) => {
+                    error!("{}", err.to_string());
+                    simple_hex(data.as_ref())
+                }
+            },
+            PayloadTy::Cbor => match codec::decode_cbor(data.as_ref())
+                .and_then(|json| Ok(serde_json::to_string_pretty(&json)?))
+            {
+                Ok(json) => json,
+                Err(err) => {
+                    error!("{}", err.to_string());
+                    simple_hex(data.as_ref())
+                }
+            },
+            PayloadTy::MsgPack => match codec::decode_msgpack(data.as_ref())
+                .and_then(|json| Ok(serde_json::to_string_pretty(&json)?))
+            {
+                Ok(json) => json,
+                Err(err) => {
+                    error!("{}", err.to_string());
+                    simple_hex(data.as_ref())
+                }
+            },
+            PayloadTy::Base64 | PayloadTy::Gzip | PayloadTy::Zstd => {
+                match self.decompress(data.as_ref()) {
+                    Ok(Some(decoded)) => {
+                        decoded_len = Some(decoded.len());
+                        to_text_or_pretty_json(decoded.as_slice())
+                    }
+                    Ok(None) => String::from_utf8_lossy(data.as_ref()).to_string(),
+                    Err(err) => {
+                        error!("{}", err.to_string());
+                        simple_hex(data.as_ref())
+                    }
+                }
+            }
+        };
+        (text, to_size(data.len(), decoded_len))
     }
-    pub fn to_bytes(&self, msg: &String) -> anyhow::Result<(Bytes, String)> {
+    /// base64解码或解压，其他类型返回None
+    fn decompress(&self, data: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
         Ok(match self {
+            PayloadTy::Base64 => Some(compress::base64_decode(data)?),
+            PayloadTy::Gzip => Some(compress::gunzip(data)?),
+            PayloadTy::Zstd => Some(compress::zstd_decompress(data)?),
+            _ => None,
+        })
+    }
+    /// 返回编码后的payload、显示内容及长度
+    pub fn to_bytes(
+        &self,
+        msg: &String,
+        proto_msg: &str,
+    ) -> anyhow::Result<(Bytes, String, AString)> {
+        let (data, text): (Bytes, String) = match self {
             PayloadTy::Text => (Bytes::from(msg.as_bytes().to_vec()), msg.clone()),
             PayloadTy::Json => (
                 Bytes::from(msg.as_bytes().to_vec()),
@@ -371,7 +1131,36 @@ impl PayloadTy {
                 }
                 (datas.into(), data_str)
             }
-        })
+            PayloadTy::Protobuf => (
+                proto::encode_from_json(proto_msg, msg.as_str())?,
+                to_pretty_json_from_str(msg.as_str())?,
+            ),
+            PayloadTy::Cbor => {
+                let json = parse_json(msg.as_str())?;
+                let mut data = Vec::new();
+                ciborium::ser::into_writer(&json, &mut data)
+                    .map_err(|e| anyhow!("fail to encode cbor: {}", e))?;
+                (data.into(), serde_json::to_string_pretty(&json)?)
+            }
+            PayloadTy::MsgPack => {
+                let json = parse_json(msg.as_str())?;
+                let data = rmp_serde::to_vec_named(&json)
+                    .map_err(|e| anyhow!("fail to encode msgpack: {}", e))?;
+                (data.into(), serde_json::to_string_pretty(&json)?)
+            }
+            PayloadTy::Base64 => (
+                Bytes::from(compress::base64_encode(msg.as_bytes())),
+                msg.clone(),
+            ),
+            PayloadTy::Gzip => (compress::gzip(msg.as_bytes())?.into(), msg.clone()),
+            PayloadTy::Zstd => (compress::zstd_compress(msg.as_bytes())?.into(), msg.clone()),
+        };
+        let decoded_len = match self {
+            PayloadTy::Base64 | PayloadTy::Gzip | PayloadTy::Zstd => Some(msg.len()),
+            _ => None,
+        };
+        let size = to_size(data.len(), decoded_len);
+        Ok((data, text, size))
     }
 }
 
@@ -380,6 +1169,28 @@ impl PayloadTy {
 //     serde_json::Ok(serde_json::to_string_pretty(&json)?)
 // }
 
+/// payload的长度，base64、压缩的类型同时显示解码后的长度
+fn to_size(len: usize, decoded_len: Option<usize>) -> AString {
+    Arc::new(match decoded_len {
+        Some(decoded_len) => format!("{}B/{}B", len, decoded_len),
+        None => format!("{}B", len),
+    })
+}
+
+/// 解码后的数据若为json则格式化，否则按字符串显示
+fn to_text_or_pretty_json(data: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(data) {
+        Ok(json) => serde_json::to_string_pretty(&json)
+            .unwrap_or_else(|_| String::from_utf8_lossy(data).to_string()),
+        Err(_) => String::from_utf8_lossy(data).to_string(),
+    }
+}
+
+/// cbor、msgpack需以json输入
+fn parse_json(data: &str) -> anyhow::Result<Value> {
+    serde_json::from_str::<Value>(data).map_err(|e| anyhow!("payload should be json: {}", e))
+}
+
 fn to_pretty_json_from_str(data: &str) -> anyhow::Result<String> {
     let json = serde_json::from_str::<Value>(data)?;
     Ok(serde_json::to_string_pretty(&json)?)
@@ -405,3 +1216,60 @@ pub enum SignedTy {
     SelfSigned,
     Insecurity,
 }
+
+/// 查看payload的格式，与订阅时选择的PayloadTy无关
+#[derive(Debug, Clone, Data, Copy, PartialEq, Eq)]
+pub enum InspectTy {
+    Text,
+    Json,
+    Hex,
+    Base64,
+}
+
+impl Default for InspectTy {
+    fn default() -> Self {
+        Self::Text
+    }
+}
+
+/// 客户端私钥的格式
+#[derive(Debug, Clone, Data, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ClientKeyTy {
+    Pkcs8,
+    Rsa,
+}
+
+impl Default for ClientKeyTy {
+    fn default() -> Self {
+        Self::Pkcs8
+    }
+}
+
+/// 客户端证书的格式，pkcs12在连接前转换为pem
+#[derive(Debug, Clone, Data, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ClientCertTy {
+    Pem,
+    Pkcs12,
+}
+
+impl Default for ClientCertTy {
+    fn default() -> Self {
+        Self::Pem
+    }
+}
+
+/// 通过保存文件对话框导出的内容
+#[derive(Debug, Clone, Data, Copy, PartialEq, Eq)]
+pub enum ExportTy {
+    Msgs,
+    Benchmark,
+}
+
+/// 通过文件对话框选择的文件
+#[derive(Debug, Clone, Data, Copy, PartialEq, Eq)]
+pub enum CertFileTy {
+    SelfSignedCa,
+    ClientCert,
+    ClientKey,
+    Pkcs12,
+}
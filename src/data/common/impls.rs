@@ -1,36 +1,28 @@
 use crate::data::common::{
-    Msg, PublicInput, PublicMsg, PublicStatus, QoS, SubscribeHis, SubscribeInput, SubscribeMsg,
-    SubscribeStatus, SubscribeTopic,
+    ActiveFilter, InspectTy, Msg, MsgDirection, MsgFilter, MsgProperties, PayloadMatcher,
+    PublicInput, PublicMsg, PublicStatus, PublishTemplate, QoS, SubscribeHis, SubscribeInput,
+    SubscribeMsg, SubscribeStatus, SubscribeTopic,
 };
+use crate::data::db::MsgKey;
 use crate::data::AString;
 use crate::mqtt;
-use crate::util::consts::QosToString;
-use crate::util::now_time;
+use crate::mqtt::data::MqttPublicInput;
+use crate::util::consts::{QosToString, QOS_1, QOS_2};
+use crate::util::{now_time, now_timestamp, time_to_timestamp};
 
+use anyhow::bail;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use pretty_hex::pretty_hex;
+use regex::Regex;
+use serde_json::Value;
 use std::sync::Arc;
+use std::time::Instant;
 
 impl SubscribeTopic {
     pub fn match_topic(&self, topic: &str) -> bool {
-        if self.topic.as_str() == "#" {
-            true
-        } else if self.topic.ends_with("/#") {
-            topic.starts_with(self.topic.split_at(self.topic.len() - 2).0)
-        } else if self.topic.as_str() == "+" {
-            if topic.contains('/') {
-                false
-            } else {
-                true
-            }
-        } else if self.topic.ends_with("/+") {
-            let sub_topic = self.topic.split_at(self.topic.len() - 2).0;
-            if topic.starts_with(sub_topic) {
-                topic.split_at(sub_topic.len()).1.contains('/')
-            } else {
-                false
-            }
-        } else {
-            self.topic.as_str() == topic
-        }
+        mqtt::topic::match_topic(self.topic.as_str(), topic)
     }
 
     pub fn from(val: SubscribeInput, trace_id: u32) -> Self {
@@ -41,6 +33,8 @@ impl SubscribeTopic {
             qos: val.qos.clone(),
             status: SubscribeStatus::SubscribeIng,
             payload_ty: val.payload_ty,
+            proto_msg: val.proto_msg.clone(),
+            fail_reason: Default::default(),
         }
     }
     pub fn from_his(val: SubscribeHis, trace_id: u32) -> Self {
@@ -51,6 +45,8 @@ impl SubscribeTopic {
             qos: val.qos.clone(),
             status: SubscribeStatus::SubscribeIng,
             payload_ty: val.payload_ty,
+            proto_msg: val.proto_msg.clone(),
+            fail_reason: Default::default(),
         }
     }
     pub fn is_sucess(&self) -> bool {
@@ -66,15 +62,59 @@ impl SubscribeTopic {
 }
 
 impl PublicMsg {
-    pub fn from(val: PublicInput, trace_id: u32) -> Self {
-        Self {
+    /// payload保存按payload_ty编码后的字节，重发时直接发送
+    pub fn from(val: PublicInput, trace_id: u32) -> anyhow::Result<Self> {
+        let (payload, msg, size) = val.payload_ty.to_bytes(&val.msg, val.proto_msg.as_str())?;
+        Ok(Self {
             trace_id,
             topic: val.topic.clone(),
-            msg: val.msg.clone(),
+            msg: Arc::new(msg),
             qos: val.qos.qos_to_string(),
             status: PublicStatus::Ing,
             payload_ty: val.payload_ty.to_arc_string(),
+            retain: val.retain,
             time: Arc::new(now_time()),
+            timestamp: now_timestamp(),
+            properties: Default::default(),
+            show_properties: false,
+            size,
+            payload: Arc::new(payload),
+            show_inspector: false,
+            inspect_ty: Default::default(),
+            sent_at: Some(Instant::now()),
+            latency: Default::default(),
+            db_key: None,
+        })
+    }
+    /// 失败或超时的消息可以重发
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.status,
+            PublicStatus::Failed(_) | PublicStatus::TimedOut
+        )
+    }
+    pub fn to_qos(&self) -> QoS {
+        if self.qos == *QOS_2 {
+            QoS::ExactlyOnce
+        } else if self.qos == *QOS_1 {
+            QoS::AtLeastOnce
+        } else {
+            QoS::AtMostOnce
+        }
+    }
+    /// 以新的trace_id重发原始的payload
+    pub fn to_retry(&mut self, broker_id: usize, trace_id: u32) -> MqttPublicInput {
+        self.trace_id = trace_id;
+        self.status = PublicStatus::Ing;
+        self.sent_at = Some(Instant::now());
+        self.latency = Default::default();
+        MqttPublicInput {
+            broker_id,
+            trace_id,
+            topic: self.topic.clone(),
+            msg: self.payload.as_ref().clone(),
+            qos: self.to_qos(),
+            retain: self.retain,
         }
     }
 }
@@ -88,6 +128,7 @@ impl From<SubscribeInput> for SubscribeHis {
             topic: val.topic.clone(),
             qos: val.qos.clone(),
             payload_ty: val.payload_ty,
+            proto_msg: val.proto_msg.clone(),
         }
     }
 }
@@ -101,6 +142,7 @@ impl From<SubscribeTopic> for SubscribeHis {
             topic: val.topic.clone(),
             qos: val.qos.clone(),
             payload_ty: val.payload_ty,
+            proto_msg: val.proto_msg.clone(),
         }
     }
 }
@@ -134,9 +176,192 @@ impl PublicInput {
             qos: QoS::AtMostOnce,
             retain: false,
             payload_ty: Default::default(),
+            proto_msg: Default::default(),
+        }
+    }
+    pub fn load_template(&mut self, template: &PublishTemplate) {
+        self.topic = template.topic.clone();
+        self.msg = template.msg.clone();
+        self.payload_ty = template.payload_ty.clone();
+        self.proto_msg = template.proto_msg.clone();
+        self.qos = template.qos.clone();
+        self.retain = template.retain;
+    }
+}
+
+impl PublishTemplate {
+    pub fn from_input(name: AString, input: &PublicInput) -> Self {
+        Self {
+            name,
+            topic: input.topic.clone(),
+            msg: input.msg.clone(),
+            payload_ty: input.payload_ty.clone(),
+            proto_msg: input.proto_msg.clone(),
+            qos: input.qos.clone(),
+            retain: input.retain,
+        }
+    }
+}
+
+impl MsgProperties {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+    pub fn user_properties_to_vec(&self) -> Vec<(String, String)> {
+        self.user_properties
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                let key = key.trim();
+                if key.is_empty() {
+                    return None;
+                }
+                Some((key.to_string(), value.trim().to_string()))
+            })
+            .collect()
+    }
+    pub fn user_properties_from_vec(properties: &[(String, String)]) -> AString {
+        Arc::new(
+            properties
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )
+    }
+    pub fn to_display(&self) -> String {
+        let mut lines = Vec::new();
+        if self.payload_format_indicator {
+            lines.push("payload format: utf8".to_string());
+        }
+        if self.message_expiry_interval > 0 {
+            lines.push(format!("message expiry: {}s", self.message_expiry_interval));
+        }
+        if !self.content_type.is_empty() {
+            lines.push(format!("content type: {}", self.content_type));
+        }
+        if !self.response_topic.is_empty() {
+            lines.push(format!("response topic: {}", self.response_topic));
+        }
+        if !self.correlation_data.is_empty() {
+            lines.push(format!("correlation data: {}", self.correlation_data));
+        }
+        for (key, value) in self.user_properties_to_vec() {
+            lines.push(format!("{}: {}", key, value));
+        }
+        lines.join("\n")
+    }
+}
+
+impl InspectTy {
+    pub fn decode(&self, payload: &Bytes) -> String {
+        match self {
+            InspectTy::Text => String::from_utf8_lossy(payload.as_ref()).to_string(),
+            InspectTy::Json => {
+                match serde_json::from_slice::<Value>(payload.as_ref())
+                    .and_then(|json| serde_json::to_string_pretty(&json))
+                {
+                    Ok(json) => json,
+                    Err(e) => format!("invalid json: {}", e),
+                }
+            }
+            InspectTy::Hex => pretty_hex(&payload.as_ref()),
+            InspectTy::Base64 => STANDARD.encode(payload.as_ref()),
+        }
+    }
+}
+
+impl MsgFilter {
+    pub fn apply(&mut self) {
+        match self.to_active() {
+            Ok(active) => {
+                self.active = active.map(Arc::new);
+                self.err = Default::default();
+            }
+            Err(e) => self.err = Arc::new(e.to_string()),
+        }
+    }
+    pub fn reset(&mut self) {
+        self.active = None;
+        self.err = Default::default();
+    }
+    fn to_active(&self) -> anyhow::Result<Option<ActiveFilter>> {
+        let topic = self.topic.trim();
+        let topic = if topic.is_empty() {
+            None
+        } else if mqtt::topic::is_valid_filter(topic) {
+            Some(topic.to_string())
+        } else {
+            bail!("invalid topic filter: {}", topic);
+        };
+        let payload = if self.payload.is_empty() {
+            None
+        } else if self.regex {
+            Some(PayloadMatcher::Regex(Regex::new(self.payload.as_str())?))
+        } else {
+            Some(PayloadMatcher::Contains(self.payload.to_string()))
+        };
+        let start = if self.start.trim().is_empty() {
+            None
+        } else {
+            Some(time_to_timestamp(self.start.as_str())?)
+        };
+        let end = if self.end.trim().is_empty() {
+            None
+        } else {
+            Some(time_to_timestamp(self.end.as_str())?)
+        };
+        if topic.is_none()
+            && payload.is_none()
+            && self.direction == MsgDirection::All
+            && self.qos.is_none()
+            && start.is_none()
+            && end.is_none()
+        {
+            return Ok(None);
+        }
+        Ok(Some(ActiveFilter {
+            topic,
+            payload,
+            direction: self.direction,
+            qos: self.qos.as_ref().map(|x| x.qos_to_string()),
+            start,
+            end,
+        }))
+    }
+}
+
+impl ActiveFilter {
+    pub fn matches(&self, msg: &Msg) -> bool {
+        match self.direction {
+            MsgDirection::Public if !msg.is_public() => return false,
+            MsgDirection::Subscribe if msg.is_public() => return false,
+            _ => {}
+        }
+        if let Some(topic) = &self.topic {
+            if !mqtt::topic::match_topic(topic.as_str(), msg.topic().as_str()) {
+                return false;
+            }
+        }
+        if let Some(qos) = &self.qos {
+            if msg.qos() != qos {
+                return false;
+            }
+        }
+        let timestamp = msg.timestamp();
+        if self.start.map_or(false, |start| timestamp < start)
+            || self.end.map_or(false, |end| timestamp > end)
+        {
+            return false;
+        }
+        match &self.payload {
+            Some(PayloadMatcher::Contains(payload)) => msg.msg().contains(payload.as_str()),
+            Some(PayloadMatcher::Regex(regex)) => regex.is_match(msg.msg().as_str()),
+            None => true,
         }
     }
 }
+
 impl Msg {
     // pub fn qos(&self) -> &QoS {
     //     match self {
@@ -156,6 +381,112 @@ impl Msg {
             Msg::Public(msg) => &msg.topic,
         }
     }
+    pub fn qos(&self) -> &AString {
+        match self {
+            Msg::Subscribe(msg) => &msg.qos,
+            Msg::Public(msg) => &msg.qos,
+        }
+    }
+    pub fn properties(&self) -> &MsgProperties {
+        match self {
+            Msg::Subscribe(msg) => &msg.properties,
+            Msg::Public(msg) => &msg.properties,
+        }
+    }
+    pub fn show_properties(&self) -> bool {
+        match self {
+            Msg::Subscribe(msg) => msg.show_properties,
+            Msg::Public(msg) => msg.show_properties,
+        }
+    }
+    pub fn size(&self) -> &AString {
+        match self {
+            Msg::Subscribe(msg) => &msg.size,
+            Msg::Public(msg) => &msg.size,
+        }
+    }
+    pub fn payload(&self) -> &Arc<Bytes> {
+        match self {
+            Msg::Subscribe(msg) => &msg.payload,
+            Msg::Public(msg) => &msg.payload,
+        }
+    }
+    pub fn show_inspector(&self) -> bool {
+        match self {
+            Msg::Subscribe(msg) => msg.show_inspector,
+            Msg::Public(msg) => msg.show_inspector,
+        }
+    }
+    pub fn toggle_inspector(&mut self) {
+        match self {
+            Msg::Subscribe(msg) => msg.show_inspector = !msg.show_inspector,
+            Msg::Public(msg) => msg.show_inspector = !msg.show_inspector,
+        }
+    }
+    /// 以选择的格式显示原始payload
+    pub fn inspect(&self) -> String {
+        match self {
+            Msg::Subscribe(msg) => msg.inspect_ty.decode(msg.payload.as_ref()),
+            Msg::Public(msg) => msg.inspect_ty.decode(msg.payload.as_ref()),
+        }
+    }
+    pub fn toggle_properties(&mut self) {
+        match self {
+            Msg::Subscribe(msg) => msg.show_properties = !msg.show_properties,
+            Msg::Public(msg) => msg.show_properties = !msg.show_properties,
+        }
+    }
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            Msg::Subscribe(msg) => msg.timestamp,
+            Msg::Public(msg) => msg.timestamp,
+        }
+    }
+    pub fn db_key(&self) -> Option<MsgKey> {
+        match self {
+            Msg::Subscribe(msg) => msg.db_key,
+            Msg::Public(msg) => msg.db_key,
+        }
+    }
+    pub fn payload_ty(&self) -> &AString {
+        match self {
+            Msg::Subscribe(msg) => &msg.payload_ty,
+            Msg::Public(msg) => &msg.payload_ty,
+        }
+    }
+    pub fn is_failed(&self) -> bool {
+        match self {
+            Msg::Subscribe(_) => false,
+            Msg::Public(msg) => msg.is_failed(),
+        }
+    }
+    /// 发布失败的原因
+    pub fn fail_reason(&self) -> String {
+        match self {
+            Msg::Public(PublicMsg {
+                status: PublicStatus::Failed(reason),
+                ..
+            }) => format!("failed: {}", reason),
+            Msg::Public(PublicMsg {
+                status: PublicStatus::TimedOut,
+                ..
+            }) => "timed out".to_string(),
+            _ => String::new(),
+        }
+    }
+    /// 发布消息收到ack的延迟，订阅消息为空
+    pub fn latency(&self) -> &str {
+        match self {
+            Msg::Subscribe(_) => "",
+            Msg::Public(msg) => msg.latency.as_str(),
+        }
+    }
+    pub fn retain(&self) -> bool {
+        match self {
+            Msg::Subscribe(msg) => msg.retain,
+            Msg::Public(msg) => msg.retain,
+        }
+    }
 }
 
 impl From<mqtt::QoS> for QoS {
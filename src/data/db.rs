@@ -1,16 +1,28 @@
 use crate::data::common::{
-    Broker, Protocol, PublicInput, SignedTy, SubscribeHis, SubscribeInput, TabStatus,
+    Broker, ClientCert, ConnectOption, Id, Msg, MsgProperties, MsgRetention, PayloadTy, Protocol,
+    PublicInput, PublicMsg, PublicStatus, PublishTemplate, QoS, SignedTy, SubscribeHis,
+    SubscribeInput, SubscribeMsg, TabStatus,
 };
 use crate::data::{AString, AppEvent};
+use crate::util::consts::QosToString;
+use crate::util::timestamp_to_time;
 use anyhow::Result;
+use bytes::Bytes;
 use crossbeam_channel::Sender;
 use druid::im::Vector;
+use druid::Data;
+use log::warn;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DbKey {
     Broker(usize),
     SubscribeHis(usize),
+    /// 消息记录的tree
+    Msgs(usize),
 }
 
 impl DbKey {
@@ -20,6 +32,9 @@ impl DbKey {
     pub fn subscribe_his_key(id: usize) -> Self {
         Self::SubscribeHis(id)
     }
+    pub fn msgs_key(id: usize) -> Self {
+        Self::Msgs(id)
+    }
     pub fn as_bytes(&self) -> Result<Vec<u8>> {
         Ok(serde_json::to_vec(self)?)
     }
@@ -54,7 +69,11 @@ pub struct BrokerDB {
     pub name: AString,
     pub addr: AString,
     pub port: Option<u16>,
+    /// 旧版本以json字符串保存的连接参数，仅用于迁移
+    #[serde(default, skip_serializing)]
     pub params: AString,
+    #[serde(default)]
+    pub option: Option<ConnectOption>,
     pub use_credentials: bool,
     pub auto_connect: bool,
     pub user_name: AString,
@@ -62,7 +81,13 @@ pub struct BrokerDB {
     pub tls: bool,
     pub signed_ty: SignedTy,
     pub self_signed_ca: AString,
+    #[serde(default)]
+    pub client_cert: ClientCert,
     pub subscribe_hises: Vector<SubscribeHis>,
+    #[serde(default)]
+    pub retention: MsgRetention,
+    #[serde(default)]
+    pub templates: Vector<PublishTemplate>,
 }
 
 impl BrokerDB {
@@ -75,15 +100,23 @@ impl BrokerDB {
             addr,
             port,
             params,
+            option,
             use_credentials,
             user_name,
             password,
             tls,
             signed_ty: ca,
             self_signed_ca,
+            client_cert,
             subscribe_hises,
             auto_connect,
+            retention,
+            templates,
         } = self;
+        let (option, err) = match option {
+            Some(option) => (option, AString::default()),
+            None => migrate_params(params.as_str()),
+        };
         Broker {
             id,
             protocol,
@@ -91,7 +124,7 @@ impl BrokerDB {
             name,
             addr,
             port,
-            params,
+            option,
             use_credentials,
             user_name,
             password,
@@ -101,6 +134,7 @@ impl BrokerDB {
             tls,
             signed_ty: ca,
             self_signed_ca,
+            client_cert,
             subscribe_hises,
             subscribe_topics: Default::default(),
             msgs: Default::default(),
@@ -111,8 +145,185 @@ impl BrokerDB {
                 id,
                 try_connect: false,
                 connected: false,
+                err,
             },
             auto_connect,
+            retention,
+            evicted: 0,
+            history_len: 0,
+            msg_filter: Default::default(),
+            filtered_cache: Default::default(),
+            paused: false,
+            paused_msgs: Default::default(),
+            msgs_bytes: 0,
+            topic_tree: Default::default(),
+            replay: Default::default(),
+            schedule: Default::default(),
+            latency: Default::default(),
+            benchmark: Default::default(),
+            templates,
+            template_name: Default::default(),
+            publish_counter: 0,
+            conn_generation: 0,
+        }
+    }
+}
+
+/// 逐个字段迁移旧版本的json参数，无法迁移的字段使用默认值，并返回提示信息
+fn migrate_params(params: &str) -> (ConnectOption, AString) {
+    let mut option = ConnectOption::default();
+    let value: Value = match serde_json::from_str(params) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("fail to migrate params, use default: {:?}", e);
+            return (
+                option,
+                Arc::new(format!("invalid params, use default: {}", e)),
+            );
+        }
+    };
+    let mut fails = Vec::new();
+    migrate_field(&value, "keep_alive", &mut option.keep_alive, &mut fails);
+    migrate_field(
+        &value,
+        "clean_session",
+        &mut option.clean_session,
+        &mut fails,
+    );
+    migrate_field(
+        &value,
+        "max_incoming_packet_size",
+        &mut option.max_incoming_packet_size,
+        &mut fails,
+    );
+    migrate_field(
+        &value,
+        "max_outgoing_packet_size",
+        &mut option.max_outgoing_packet_size,
+        &mut fails,
+    );
+    migrate_field(&value, "conn_timeout", &mut option.conn_timeout, &mut fails);
+    if fails.is_empty() {
+        return (option, AString::default());
+    }
+    warn!("fail to migrate params {:?}, use default", fails);
+    let err = format!("params {} not migrated, use default", fails.join(", "));
+    (option, Arc::new(err))
+}
+
+fn migrate_field<T: DeserializeOwned>(
+    value: &Value,
+    name: &'static str,
+    field: &mut T,
+    fails: &mut Vec<&'static str>,
+) {
+    match value.get(name).map(|x| T::deserialize(x)) {
+        Some(Ok(val)) => *field = val,
+        _ => fails.push(name),
+    }
+}
+
+/// 消息方向：发布或者接收
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MsgTy {
+    Public,
+    Subscribe,
+}
+
+/// 消息在sled中的key：毫秒时间戳+自增id，同一毫秒内的消息也能区分先后
+#[derive(Debug, Clone, Copy, Data, Eq, PartialEq)]
+pub struct MsgKey {
+    pub timestamp: i64,
+    pub id: u64,
+}
+
+impl MsgKey {
+    pub fn to_bytes(self) -> Vec<u8> {
+        let mut key = Vec::with_capacity(16);
+        key.extend_from_slice(&(self.timestamp as u64).to_be_bytes());
+        key.extend_from_slice(&self.id.to_be_bytes());
+        key
+    }
+    pub fn from_bytes(key: &[u8]) -> Option<Self> {
+        let timestamp = u64::from_be_bytes(key.get(..8)?.try_into().ok()?) as i64;
+        let id = u64::from_be_bytes(key.get(8..16)?.try_into().ok()?);
+        Some(Self { timestamp, id })
+    }
+}
+
+/// 持久化的消息记录，保留原始payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MsgDB {
+    pub ty: MsgTy,
+    pub topic: AString,
+    pub payload: Vec<u8>,
+    pub payload_ty: PayloadTy,
+    #[serde(default)]
+    pub proto_msg: AString,
+    pub qos: QoS,
+    pub retain: bool,
+    /// 毫秒
+    pub timestamp: i64,
+    #[serde(default)]
+    pub properties: MsgProperties,
+}
+
+impl MsgDB {
+    pub fn to_msg(self, db_key: MsgKey) -> Msg {
+        let Self {
+            ty,
+            topic,
+            payload,
+            payload_ty,
+            proto_msg,
+            qos,
+            retain,
+            timestamp,
+            properties,
+        } = self;
+        let payload = Arc::new(Bytes::from(payload));
+        let (msg, size) = payload_ty.format(payload.clone(), proto_msg.as_str());
+        let msg = Arc::new(msg);
+        let time = Arc::new(timestamp_to_time(timestamp));
+        match ty {
+            MsgTy::Public => PublicMsg {
+                trace_id: Id::to_id(),
+                topic,
+                msg,
+                qos: qos.qos_to_string(),
+                status: PublicStatus::Success,
+                payload_ty: payload_ty.to_arc_string(),
+                retain,
+                time,
+                timestamp,
+                properties,
+                show_properties: false,
+                size,
+                payload,
+                show_inspector: false,
+                inspect_ty: Default::default(),
+                sent_at: None,
+                latency: Default::default(),
+                db_key: Some(db_key),
+            }
+            .into(),
+            MsgTy::Subscribe => SubscribeMsg {
+                topic,
+                msg,
+                qos: qos.qos_to_string(),
+                payload_ty: payload_ty.to_arc_string(),
+                retain,
+                time,
+                timestamp,
+                properties,
+                show_properties: false,
+                size,
+                payload,
+                show_inspector: false,
+                inspect_ty: Default::default(),
+                db_key: Some(db_key),
+            }
+            .into(),
         }
     }
 }
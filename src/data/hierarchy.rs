@@ -1,13 +1,21 @@
-use crate::data::common::{Broker, Id, PayloadTy, QoS};
+use crate::data::common::{Broker, CertFileTy, ExportTy, Id, MsgProperties, PayloadTy, QoS};
 use crate::data::common::{
-    Msg, PublicMsg, PublicStatus, SubscribeHis, SubscribeMsg, SubscribeStatus, SubscribeTopic,
+    Msg, PublicInput, PublicMsg, PublicStatus, SubscribeHis, SubscribeMsg, SubscribeStatus,
+    SubscribeTopic,
 };
+use crate::data::db::{MsgDB, MsgKey, MsgTy};
 use crate::data::{AString, AppEvent, EventUnSubscribe};
+use crate::mqtt::benchmark::BenchReport;
 use crate::mqtt::data::MqttPublicInput;
+use crate::mqtt::subscribe_fail_reason;
 use crate::util::consts::QosToString;
 use crate::util::db::ArcDb;
+use crate::util::export::{export_json, export_msgs};
 use crate::util::hint::*;
-use crate::util::now_time;
+use crate::util::latency::format_latency;
+use crate::util::replay::ReplayMsg;
+use crate::util::template;
+use crate::util::{now_time, now_timestamp};
 use anyhow::Result;
 use anyhow::{anyhow, bail};
 use bytes::Bytes;
@@ -17,8 +25,15 @@ use druid::im::Vector;
 use druid::{Data, Lens};
 use for_mqtt_client::protocol::packet::SubscribeReasonCode;
 use for_mqtt_client::SubscribeAck;
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
+
+/// 每次向前翻页加载的历史消息条数
+const HISTORY_PAGE_SIZE: usize = 50;
+/// 已加载历史消息的上限，超出时丢弃离实时消息最近的部分
+const MAX_HISTORY_LEN: usize = HISTORY_PAGE_SIZE * 20;
 //
 // #[derive(Debug, Clone, Lens, Data)]
 // pub struct AppData {
@@ -50,9 +65,15 @@ pub struct AppData {
     #[lens(ignore)]
     pub db: ArcDb,
     pub hint: AString,
-    /// #[data(ignore)] 不能加这个，不然就无法改变self_signed_file。为什么？不知道！简单的案例无法复现出来。
+    /// #[data(ignore)] 不能加这个，不然就无法改变cert_file。为什么？不知道！简单的案例无法复现出来。
+    #[lens(ignore)]
+    pub cert_file: Option<(usize, CertFileTy)>,
+    /// 等待选择导出文件的broker
     #[lens(ignore)]
-    pub self_signed_file: Option<usize>,
+    pub export_broker: Option<(usize, ExportTy)>,
+    /// 等待选择回放文件的broker
+    #[lens(ignore)]
+    pub replay_broker: Option<usize>,
     pub display_history: bool,
     pub display_broker_info: bool,
     #[data(ignore)]
@@ -71,12 +92,60 @@ impl AppData {
             error!("fail to send event: {:?}", e.0)
         }
     }
-    pub fn set_self_signed_file(&mut self, index: usize) {
-        self.self_signed_file = Some(index);
-        self.self_signed_file.replace(index);
+    pub fn set_cert_file(&mut self, index: usize, ty: CertFileTy) {
+        self.replay_broker = None;
+        self.cert_file = Some((index, ty));
+    }
+    pub fn set_replay_broker(&mut self, id: usize) {
+        self.cert_file = None;
+        self.replay_broker = Some(id);
+    }
+    /// 将选择的文件设为回放文件，没有等待的broker时返回false
+    pub fn set_replay_file(&mut self, path: AString) -> bool {
+        let Some(id) = self.replay_broker.take() else {
+            return false;
+        };
+        match self.find_mut_broker_by_id(id) {
+            Ok(broker) => broker.replay.file = path,
+            Err(e) => error!("{:?}", e),
+        }
+        true
+    }
+    pub fn get_cert_file(&self) -> Option<(usize, CertFileTy)> {
+        self.cert_file.clone()
     }
-    pub fn get_self_signed_file(&self) -> Option<usize> {
-        self.self_signed_file.clone()
+    pub fn set_export_broker(&mut self, id: usize, ty: ExportTy) {
+        self.export_broker = Some((id, ty));
+    }
+    /// 将等待导出的broker的消息或压测结果写入文件
+    pub fn export(&mut self, path: &Path) -> Result<()> {
+        let Some((id, ty)) = self.export_broker.take() else {
+            return Ok(());
+        };
+        self.hint = Arc::new(match ty {
+            ExportTy::Msgs => {
+                let count = export_msgs(path, self.db.iter_msgs(id)?)?;
+                format!("exported {} msgs to {}", count, path.display())
+            }
+            ExportTy::Benchmark => {
+                let broker = self.find_broker_by_id(id)?;
+                let Some(report) = broker.benchmark.report.clone() else {
+                    bail!("no benchmark result");
+                };
+                export_json(path, report.as_ref())?;
+                format!("exported benchmark result to {}", path.display())
+            }
+        });
+        Ok(())
+    }
+    pub fn benchmark_finished(&mut self, id: usize, rs: Result<BenchReport>) -> Result<()> {
+        let broker = self.find_mut_broker_by_id(id)?;
+        broker.benchmark.running = false;
+        let report = rs?;
+        info!("benchmark finished");
+        broker.benchmark.summary = Arc::new(report.summary());
+        broker.benchmark.report = Some(Arc::new(report));
+        Ok(())
     }
     pub fn touch_add_broker(&mut self) {
         self.unselect_broker();
@@ -218,11 +287,34 @@ impl AppData {
         let status = &mut broker.tab_status;
         status.try_connect = false;
         status.connected = true;
+        status.err = Default::default();
         if !_retain {
             broker.subscribe_topics.clear();
         }
         Ok(())
     }
+    /// 连接失败(超时、broker拒绝等)：更新状态。
+    /// 开启自动重连时client会继续重连，只有首次连接失败才释放client
+    pub fn connect_fail(
+        &mut self,
+        id: usize,
+        generation: u64,
+        err: AString,
+        initial: bool,
+    ) -> Result<()> {
+        let broker = self.find_mut_broker_by_id(id)?;
+        if broker.conn_generation != generation {
+            debug!("ignore connect fail of a previous connection: {}", err);
+            return Ok(());
+        }
+        broker.tab_status.err = err;
+        if broker.auto_connect && !initial {
+            broker.tab_status.connected = false;
+            return Ok(());
+        }
+        broker.disconnect(false);
+        self.disconnect(id)
+    }
     pub(crate) fn touch_disconnect(&mut self) -> Result<()> {
         let broker = self.get_selected_mut_broker()?;
         broker.disconnect(false);
@@ -374,6 +466,20 @@ impl AppData {
         warn!("{}", DELETE_SUBSCRIBE_NO_SELECTED);
         Ok(())
     }
+    pub fn touch_save_template(&mut self, id: usize) -> Result<()> {
+        let broker = self.find_mut_broker_by_id(id)?;
+        broker.save_template()?;
+        let broker = broker.clone_to_db();
+        self.db.save_broker(broker)?;
+        Ok(())
+    }
+    pub fn touch_remove_template(&mut self, id: usize, name: AString) -> Result<()> {
+        let broker = self.find_mut_broker_by_id(id)?;
+        broker.templates.retain(|x| x.name != name);
+        let broker = broker.clone_to_db();
+        self.db.save_broker(broker)?;
+        Ok(())
+    }
     pub fn touch_click_tab(&mut self, broker_id: usize) -> Result<()> {
         self.select_broker(broker_id);
         Ok(())
@@ -381,73 +487,168 @@ impl AppData {
 
     pub fn sub_ack(&mut self, id: usize, input: SubscribeAck) -> Result<()> {
         let broker = self.find_mut_broker_by_id(id)?;
-        let SubscribeAck { id, mut acks } = input;
-        if let Some(ack) = acks.pop() {
-            if let Some(subscribe_topic) = broker
-                .subscribe_topics
-                .iter_mut()
-                .find(|x| x.trace_id == id)
-            {
-                match ack {
-                    SubscribeReasonCode::QoS0 => {
-                        subscribe_topic.qos = QoS::AtMostOnce.clone();
-                        subscribe_topic.status = SubscribeStatus::SubscribeSuccess;
-                    }
-                    SubscribeReasonCode::QoS1 => {
-                        subscribe_topic.qos = QoS::AtLeastOnce.clone();
-                        subscribe_topic.status = SubscribeStatus::SubscribeSuccess;
-                    }
-                    SubscribeReasonCode::QoS2 => {
-                        subscribe_topic.qos = QoS::ExactlyOnce.clone();
-                        subscribe_topic.status = SubscribeStatus::SubscribeSuccess;
-                    }
-                    _reasone => {
-                        subscribe_topic.status = SubscribeStatus::SubscribeFail;
-                    }
-                }
-            } else {
-                warn!("could not find subscribe");
+        let SubscribeAck { id, acks } = input;
+        let Some(subscribe_topic) = broker
+            .subscribe_topics
+            .iter_mut()
+            .find(|x| x.trace_id == id)
+        else {
+            warn!("could not find subscribe");
+            return Ok(());
+        };
+        let Some(ack) = acks.first() else {
+            bail!("suback of {} has no reason code", subscribe_topic.topic);
+        };
+        let qos = match ack {
+            SubscribeReasonCode::QoS0 => QoS::AtMostOnce,
+            SubscribeReasonCode::QoS1 => QoS::AtLeastOnce,
+            SubscribeReasonCode::QoS2 => QoS::ExactlyOnce,
+            reason => {
+                let reason = subscribe_fail_reason(reason);
+                warn!("subscribe {} fail: {}", subscribe_topic.topic, reason);
+                subscribe_topic.status = SubscribeStatus::SubscribeFail;
+                subscribe_topic.fail_reason = Arc::new(reason);
+                return Ok(());
+            }
+        };
+        subscribe_topic.qos = qos;
+        subscribe_topic.status = SubscribeStatus::SubscribeSuccess;
+        subscribe_topic.fail_reason = Default::default();
+        Ok(())
+    }
+    /// client报告的订阅失败不带trace_id，只有一条订阅在等待ack时才能确定是哪一条
+    pub fn sub_fail(&mut self, id: usize, reason: String) -> Result<()> {
+        let broker = self.find_mut_broker_by_id(id)?;
+        let mut subscribing = broker
+            .subscribe_topics
+            .iter_mut()
+            .filter(|x| x.status == SubscribeStatus::SubscribeIng);
+        match (subscribing.next(), subscribing.next()) {
+            (Some(subscribe_topic), None) => {
+                warn!("subscribe {} fail: {}", subscribe_topic.topic, reason);
+                subscribe_topic.status = SubscribeStatus::SubscribeFail;
+                subscribe_topic.fail_reason = Arc::new(reason);
             }
+            _ => error!("broker({}) subscribe fail: {}", id, reason),
         }
         Ok(())
-        // } else {
-        //     warn!("could not find subscribe");
-        // }
     }
-    pub fn publish(&mut self, id: usize) -> Result<()> {
+    /// 返回发布的trace_id
+    pub fn publish(&mut self, id: usize) -> Result<u32> {
+        let input = self.find_broker_by_id(id)?.public_input.clone();
+        self.publish_input(id, input)
+    }
+    fn publish_input(&mut self, id: usize, input: PublicInput) -> Result<u32> {
         let broker = self.find_mut_broker_by_id(id)?;
-        let (payload, payload_str) = broker
-            .public_input
+        broker.publish_counter += 1;
+        let counter = broker.publish_counter;
+        let topic = Arc::new(template::expand(input.topic.as_str(), counter)?);
+        let msg = template::expand(input.msg.as_str(), counter)?;
+        let (payload, payload_str, size) =
+            input.payload_ty.to_bytes(&msg, input.proto_msg.as_str())?;
+        self.publish_msg(
+            id,
+            topic,
+            payload,
+            payload_str,
+            size,
+            input.payload_ty,
+            input.proto_msg,
+            input.qos,
+            input.retain,
+        )
+    }
+    /// 回放记录中的消息，与手动发布一样记录并等待ack
+    pub fn replay_publish(&mut self, id: usize, row: ReplayMsg) -> Result<u32> {
+        let (payload_str, size) = row
             .payload_ty
-            .to_bytes(&broker.public_input.msg)?;
+            .format(Arc::new(row.payload.clone()), row.proto_msg.as_str());
+        self.publish_msg(
+            id,
+            row.topic,
+            row.payload,
+            payload_str,
+            size,
+            row.payload_ty,
+            row.proto_msg,
+            row.qos,
+            row.retain,
+        )
+    }
+    #[allow(clippy::too_many_arguments)]
+    fn publish_msg(
+        &mut self,
+        id: usize,
+        topic: AString,
+        payload: Bytes,
+        payload_str: String,
+        size: AString,
+        payload_ty: PayloadTy,
+        proto_msg: AString,
+        qos: QoS,
+        retain: bool,
+    ) -> Result<u32> {
         let trace_id = Id::to_id();
-        let msg = PublicMsg {
+        let timestamp = now_timestamp();
+        let mut msg = PublicMsg {
             trace_id,
-            topic: broker.public_input.topic.clone(),
+            topic: topic.clone(),
             msg: Arc::new(payload_str),
-            qos: broker.public_input.qos.qos_to_string(),
+            qos: qos.qos_to_string(),
             status: PublicStatus::Ing,
-            payload_ty: broker.public_input.payload_ty.to_arc_string(),
+            payload_ty: payload_ty.to_arc_string(),
+            retain,
             time: Arc::new(now_time()),
+            timestamp,
+            properties: Default::default(),
+            show_properties: false,
+            size,
+            payload: Arc::new(payload.clone()),
+            show_inspector: false,
+            inspect_ty: Default::default(),
+            sent_at: Some(Instant::now()),
+            latency: Default::default(),
+            db_key: None,
+        };
+        let record = MsgDB {
+            ty: MsgTy::Public,
+            topic: topic.clone(),
+            payload: payload.to_vec(),
+            payload_ty,
+            proto_msg,
+            qos: qos.clone(),
+            retain,
+            timestamp,
+            properties: Default::default(),
         };
         debug!("publish: tarce_id {}", trace_id);
+        msg.db_key = self.save_msg(id, &record);
 
-        broker.msgs.push_back(msg.into());
-        if broker.msgs.len() > 50 {
-            broker.msgs.pop_front();
-        }
+        let broker = self.find_mut_broker_by_id(id)?;
+        broker.push_msg(msg.into());
 
         let publish = MqttPublicInput {
             broker_id: broker.id,
             trace_id,
-            topic: broker.public_input.topic.clone(),
+            topic,
             msg: payload,
-            qos: broker.public_input.qos.clone(),
-            retain: broker.public_input.retain,
+            qos,
+            retain,
         };
+        let paused = broker.paused;
 
         self.send_event(AppEvent::ToPublish(publish));
-        self.send_event(AppEvent::UpdateScrollMsgWin);
+        if !paused {
+            self.send_event(AppEvent::UpdateScrollMsgWin);
+        }
+        Ok(trace_id)
+    }
+    /// 定时发布的一次发布，input为开始时的发布输入，记录trace_id用于统计ack
+    pub fn schedule_publish(&mut self, id: usize, input: PublicInput) -> Result<()> {
+        let trace_id = self.publish_input(id, input)?;
+        let schedule = &mut self.find_mut_broker_by_id(id)?.schedule;
+        schedule.sent += 1;
+        schedule.trace_ids.insert(trace_id);
         Ok(())
     }
     pub fn click_broker(&mut self, id: usize) -> Result<()> {
@@ -551,6 +752,14 @@ impl AppData {
         Ok(())
     }
 
+    /// 点击topic树的节点，将topic填入订阅和发布的输入框
+    pub fn click_topic_node(&mut self, id: usize, topic: AString) -> Result<()> {
+        let broker = self.find_mut_broker_by_id(id)?;
+        broker.subscribe_input.topic = topic.clone();
+        broker.public_input.topic = topic;
+        Ok(())
+    }
+
     pub fn touch_close_broker_tab(&mut self, id: usize) -> Result<()> {
         self.close_broker_tab(id)?;
         self.find_mut_broker_by_id(id)?.disconnect(true);
@@ -569,12 +778,20 @@ impl AppData {
     pub fn pub_ack(&mut self, id: usize, trace_id: u32) -> Result<()> {
         debug!("pub_ack: tarce_id {}", trace_id);
         let broker = self.find_mut_broker_by_id(id)?;
+        if broker.schedule.trace_ids.remove(&trace_id) {
+            broker.schedule.acked += 1;
+        }
         let mut is_ack = false;
-        for msg in broker.msgs.iter_mut() {
+        for msg in broker.msgs.iter_mut().chain(broker.paused_msgs.iter_mut()) {
             if let Msg::Public(msg) = msg {
                 if msg.trace_id == trace_id {
                     is_ack = true;
                     msg.status = PublicStatus::Success;
+                    if let Some(sent_at) = msg.sent_at.take() {
+                        let latency = sent_at.elapsed();
+                        msg.latency = Arc::new(format_latency(latency));
+                        broker.latency.record(latency);
+                    }
                 }
             }
         }
@@ -583,40 +800,167 @@ impl AppData {
         }
         Ok(())
     }
+    /// 发布请求提交失败
+    pub fn pub_fail(&mut self, id: usize, trace_id: u32, reason: AString) -> Result<()> {
+        let broker = self.find_mut_broker_by_id(id)?;
+        let msg = broker
+            .msgs
+            .iter_mut()
+            .chain(broker.paused_msgs.iter_mut())
+            .find_map(|msg| match msg {
+                Msg::Public(msg) if msg.trace_id == trace_id => Some(msg),
+                _ => None,
+            });
+        let Some(msg) = msg else {
+            bail!("pub_fail could not find pub({}): {}", trace_id, reason);
+        };
+        msg.status = PublicStatus::Failed(reason);
+        msg.sent_at = None;
+        broker.schedule.trace_ids.remove(&trace_id);
+        Ok(())
+    }
+    /// 超时仍未收到ack的消息标记为超时，已ack或已清空的忽略
+    pub fn pub_timeout(&mut self, id: usize, trace_id: u32) -> Result<()> {
+        let broker = self.find_mut_broker_by_id(id)?;
+        for msg in broker.msgs.iter_mut().chain(broker.paused_msgs.iter_mut()) {
+            if let Msg::Public(msg) = msg {
+                if msg.trace_id == trace_id && msg.status == PublicStatus::Ing {
+                    warn!("publish({}) timed out", trace_id);
+                    msg.status = PublicStatus::TimedOut;
+                    msg.sent_at = None;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// 以新的trace_id重发失败或超时的消息
+    pub fn retry_publish(&mut self, trace_id: u32) -> Result<()> {
+        let new_trace_id = Id::to_id();
+        let mut publish = None;
+        'brokers: for broker in self.brokers.iter_mut() {
+            for msg in broker.msgs.iter_mut().chain(broker.paused_msgs.iter_mut()) {
+                let Msg::Public(msg) = msg else {
+                    continue;
+                };
+                if msg.trace_id != trace_id {
+                    continue;
+                }
+                if !msg.is_failed() {
+                    bail!("pub({}) has not failed", trace_id);
+                }
+                publish = Some(msg.to_retry(broker.id, new_trace_id));
+                if broker.schedule.trace_ids.remove(&trace_id) {
+                    broker.schedule.trace_ids.insert(new_trace_id);
+                }
+                break 'brokers;
+            }
+        }
+        let Some(publish) = publish else {
+            bail!("retry could not find pub({})", trace_id);
+        };
+        debug!("retry publish: tarce_id {} -> {}", trace_id, new_trace_id);
+        self.send_event(AppEvent::ToPublish(publish));
+        Ok(())
+    }
     pub fn receive_msg(
         &mut self,
         id: usize,
         topic: Arc<String>,
         payload: Arc<Bytes>,
         qos: QoS,
+        retain: bool,
+        properties: MsgProperties,
     ) -> Result<()> {
         let broker = self.find_mut_broker_by_id(id)?;
-        let payload_ty = if let Some(subscribe) = broker
+        let (payload_ty, proto_msg) = if let Some(subscribe) = broker
             .subscribe_topics
             .iter()
             .find(|x| x.match_topic(topic.as_str()))
         {
-            subscribe.payload_ty.clone()
+            (subscribe.payload_ty.clone(), subscribe.proto_msg.clone())
         } else {
             warn!("could not find this publish's subscribe record");
-            PayloadTy::default()
+            (PayloadTy::default(), Default::default())
+        };
+        let timestamp = now_timestamp();
+        let record = MsgDB {
+            ty: MsgTy::Subscribe,
+            topic: topic.clone(),
+            payload: payload.to_vec(),
+            payload_ty: payload_ty.clone(),
+            proto_msg: proto_msg.clone(),
+            qos: qos.clone(),
+            retain,
+            timestamp,
+            properties: properties.clone(),
         };
-        let payload = payload_ty.format(payload);
+        let (payload_str, size) = payload_ty.format(payload.clone(), proto_msg.as_str());
+        let payload_str = Arc::new(payload_str);
+        let time = Arc::new(now_time());
+        broker.record_topic(topic.as_str(), payload_str.clone(), time.clone(), retain);
         let msg = SubscribeMsg {
             topic,
-            msg: Arc::new(payload),
+            msg: payload_str,
             qos: qos.qos_to_string(),
             payload_ty: payload_ty.to_arc_string(),
-            time: Arc::new(now_time()),
+            retain,
+            time,
+            timestamp,
+            properties,
+            show_properties: false,
+            size,
+            payload,
+            show_inspector: false,
+            inspect_ty: Default::default(),
+            db_key: self.save_msg(id, &record),
         };
-        broker.msgs.push_back(msg.into());
-        if broker.msgs.len() > 50 {
-            broker.msgs.pop_front();
+        let broker = self.find_mut_broker_by_id(id)?;
+        broker.push_msg(msg.into());
+        if broker.paused {
+            return Ok(());
         }
         Ok(self.db.tx.send(AppEvent::UpdateScrollMsgWin)?)
     }
+    fn save_msg(&self, id: usize, record: &MsgDB) -> Option<MsgKey> {
+        match self.db.save_msg(id, record) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                error!("fail to save msg: {:?}", e);
+                None
+            }
+        }
+    }
+    /// 向前翻页：加载早于当前最早一条消息的历史记录
+    pub fn load_history_msg(&mut self, id: usize) -> Result<usize> {
+        let before = self.find_broker_by_id(id)?.msgs.front().map(|x| {
+            x.db_key().unwrap_or(MsgKey {
+                timestamp: x.timestamp(),
+                id: 0,
+            })
+        });
+        let records = self.db.read_msgs(id, before, HISTORY_PAGE_SIZE)?;
+        let broker = self.find_mut_broker_by_id(id)?;
+        let len = records.len();
+        for (key, record) in records.into_iter().rev() {
+            broker.msgs.push_front(record.to_msg(key));
+        }
+        broker.history_len += len;
+        if broker.history_len > MAX_HISTORY_LEN {
+            let rest = broker.msgs.split_off(broker.history_len);
+            broker.msgs.truncate(MAX_HISTORY_LEN);
+            broker.msgs.append(rest);
+            broker.history_len = MAX_HISTORY_LEN;
+        }
+        Ok(len)
+    }
     pub fn clear_msg(&mut self, id: usize) -> Result<()> {
-        let _broker = self.find_mut_broker_by_id(id)?.msgs.clear();
+        let broker = self.find_mut_broker_by_id(id)?;
+        broker.msgs.clear();
+        broker.msgs_bytes = 0;
+        broker.history_len = 0;
+        broker.paused_msgs.clear();
+        broker.latency.clear();
+        broker.evicted = 0;
         Ok(self.db.tx.send(AppEvent::UpdateScrollMsgWin)?)
     }
 
@@ -1,9 +1,12 @@
-use crate::data::common::{Broker, QoS};
-use crate::data::common::{Msg, PublicInput, SubscribeHis, SubscribeInput, SubscribeTopic};
+use crate::data::common::{Broker, InspectTy, QoS, TopicNode};
+use crate::data::common::{
+    Msg, PublicInput, PublishTemplate, SubscribeHis, SubscribeInput, SubscribeTopic,
+};
 use crate::data::hierarchy::AppData;
 use crate::data::AString;
 use crate::util::consts::QosToString;
 use druid::im::Vector;
+use druid::Data;
 use druid::Lens;
 
 
@@ -298,6 +301,116 @@ impl Lens<Msg, Arc<String>> for MsgQosLens {
         f(qos)
     }
 }
+/// 按过滤条件显示消息，列表中的修改(如展开properties)会写回对应的消息
+pub struct BrokerFilteredMsgs;
+impl Lens<Broker, Vector<Msg>> for BrokerFilteredMsgs {
+    fn with<V, F: FnOnce(&Vector<Msg>) -> V>(&self, data: &Broker, f: F) -> V {
+        let Some(filter) = &data.msg_filter.active else {
+            return f(&data.msgs);
+        };
+        let (_, msgs) = data.filtered_msgs(filter);
+        f(&msgs)
+    }
+
+    fn with_mut<V, F: FnOnce(&mut Vector<Msg>) -> V>(&self, data: &mut Broker, f: F) -> V {
+        let Some(filter) = data.msg_filter.active.clone() else {
+            return f(&mut data.msgs);
+        };
+        let (indexes, mut msgs) = data.filtered_msgs(&filter);
+        let rs = f(&mut msgs);
+        for (index, msg) in indexes.iter().copied().zip(msgs.into_iter()) {
+            if !data.msgs[index].same(&msg) {
+                data.msgs[index] = msg;
+            }
+        }
+        rs
+    }
+}
+/// topic树中可见的节点：折叠节点的子孙不显示，列表中的修改(如展开)会写回对应的节点
+pub struct VisibleTopicNodes;
+impl VisibleTopicNodes {
+    fn indexes(nodes: &Vector<TopicNode>) -> Vec<usize> {
+        let mut collapsed_depth: Option<usize> = None;
+        let mut indexes = Vec::new();
+        for (index, node) in nodes.iter().enumerate() {
+            if let Some(depth) = collapsed_depth {
+                if node.depth > depth {
+                    continue;
+                }
+                collapsed_depth = None;
+            }
+            if node.has_children && !node.expanded {
+                collapsed_depth = Some(node.depth);
+            }
+            indexes.push(index);
+        }
+        indexes
+    }
+}
+impl Lens<Broker, Vector<TopicNode>> for VisibleTopicNodes {
+    fn with<V, F: FnOnce(&Vector<TopicNode>) -> V>(&self, data: &Broker, f: F) -> V {
+        let nodes: Vector<TopicNode> = Self::indexes(&data.topic_tree)
+            .into_iter()
+            .map(|x| data.topic_tree[x].clone())
+            .collect();
+        f(&nodes)
+    }
+
+    fn with_mut<V, F: FnOnce(&mut Vector<TopicNode>) -> V>(&self, data: &mut Broker, f: F) -> V {
+        let indexes = Self::indexes(&data.topic_tree);
+        let mut nodes: Vector<TopicNode> = indexes
+            .iter()
+            .map(|x| data.topic_tree[*x].clone())
+            .collect();
+        let rs = f(&mut nodes);
+        for (index, node) in indexes.into_iter().zip(nodes.into_iter()) {
+            if !data.topic_tree[index].same(&node) {
+                data.topic_tree[index] = node;
+            }
+        }
+        rs
+    }
+}
+/// 模板列表与发布输入，点击模板时加载到发布输入
+pub struct BrokerTemplatesLens;
+impl Lens<Broker, (PublicInput, Vector<PublishTemplate>)> for BrokerTemplatesLens {
+    fn with<V, F: FnOnce(&(PublicInput, Vector<PublishTemplate>)) -> V>(
+        &self,
+        data: &Broker,
+        f: F,
+    ) -> V {
+        f(&(data.public_input.clone(), data.templates.clone()))
+    }
+
+    fn with_mut<V, F: FnOnce(&mut (PublicInput, Vector<PublishTemplate>)) -> V>(
+        &self,
+        data: &mut Broker,
+        f: F,
+    ) -> V {
+        let mut val = (data.public_input.clone(), data.templates.clone());
+        let rs = f(&mut val);
+        if !data.public_input.same(&val.0) {
+            data.public_input = val.0;
+        }
+        rs
+    }
+}
+pub struct MsgInspectTyLens;
+impl Lens<Msg, InspectTy> for MsgInspectTyLens {
+    fn with<V, F: FnOnce(&InspectTy) -> V>(&self, data: &Msg, f: F) -> V {
+        f(match data {
+            Msg::Public(msg) => &msg.inspect_ty,
+            Msg::Subscribe(msg) => &msg.inspect_ty,
+        })
+    }
+
+    fn with_mut<V, F: FnOnce(&mut InspectTy) -> V>(&self, data: &mut Msg, f: F) -> V {
+        f(match data {
+            Msg::Public(msg) => &mut msg.inspect_ty,
+            Msg::Subscribe(msg) => &mut msg.inspect_ty,
+        })
+    }
+}
 pub struct MsgPayloadTyLens;
 impl Lens<Msg, AString> for MsgPayloadTyLens {
     fn with<V, F: FnOnce(&AString) -> V>(&self, data: &Msg, f: F) -> V {
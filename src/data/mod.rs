@@ -6,11 +6,13 @@ pub mod lens;
 pub mod localized;
 
 use crate::data::click_ty::ClickTy;
-use crate::data::common::{QoS, SubscribeHis, SubscribeTopic};
+use crate::data::common::{MsgProperties, QoS, ScheduleConfig, SubscribeHis, SubscribeTopic};
 use bytes::Bytes;
 use common::Broker;
 
+use crate::mqtt::benchmark::BenchConfig;
 use crate::mqtt::data::MqttPublicInput;
+use crate::util::replay::ReplayConfig;
 use for_mqtt_client::{SubscribeAck, UnsubscribeAck};
 use std::sync::Arc;
 
@@ -64,13 +66,29 @@ pub enum AppEvent {
         broker_id: usize,
         retain: bool,
     },
-    ClientConnectAckFail(usize, Arc<String>),
+    /// initial: 首次连接失败(创建client失败或等待connack超时)
+    /// generation: 发起连接时broker的conn_generation
+    ClientConnectAckFail {
+        broker_id: usize,
+        generation: u64,
+        err: Arc<String>,
+        initial: bool,
+    },
     ClientConnectedErr(usize, String),
     ClientDisconnect(usize),
     TouchPublic(usize),
-    ClientReceivePublic(usize, Arc<String>, Arc<Bytes>, QoS),
+    /// broker_id, topic, payload, qos, retain, properties(v5)
+    ClientReceivePublic(usize, Arc<String>, Arc<Bytes>, QoS, bool, MsgProperties),
     ClientPubAck(usize, u32),
+    /// 定时检查发布是否超时未收到ack
+    SweepPublishTimeout,
+    /// client报告的发布失败(broker_id, reason)，不带trace_id
+    ClientPublishFail(usize, String),
+    /// 重发失败或超时的消息(trace_id)
+    TouchRetryPublish(u32),
     ClientSubAck(usize, SubscribeAck),
+    /// client报告的订阅失败(broker_id, reason)，不带trace_id
+    ClientSubscribeFail(usize, String),
     ClientUnSubAck(usize, UnsubscribeAck),
     TouchClick(ClickTy),
     OtherClickLifeDead(ClickTy),
@@ -79,10 +97,28 @@ pub enum AppEvent {
     UpdateStatusBar(String),
     /// 清空消息
     TouchClearMsg(usize),
+    /// 加载更早的历史消息
+    TouchLoadHistoryMsg(usize),
     /// 滚动消息窗口
     UpdateScrollMsgWin,
     /// 滚动订阅窗口
     UpdateScrollSubscribeWin,
+    /// 开始回放记录
+    ToReplay(usize, ReplayConfig),
+    /// 停止回放
+    ToStopReplay(usize),
+    /// 开始定时发布
+    ToStartSchedule(usize, ScheduleConfig),
+    /// 停止定时发布
+    ToStopSchedule(usize),
+    /// 以broker的配置开始压测
+    ToBenchmark(Broker, BenchConfig),
+    /// 停止压测
+    ToStopBenchmark(usize),
+    /// 以当前的发布输入保存模板
+    TouchSaveTemplate(usize),
+    /// 删除模板(broker_id, name)
+    TouchRemoveTemplate(usize, AString),
 }
 #[derive(Debug, Clone)]
 pub struct EventUnSubscribe {
@@ -20,4 +20,8 @@ pub enum ForError {
     InvalidQos,
     #[error("Not Empty")]
     NotEmpty,
+    #[error("Only number")]
+    InvalidNumber,
+    #[error("At least 5")]
+    InvalidKeepAlive,
 }
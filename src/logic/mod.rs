@@ -1,21 +1,25 @@
 use crate::data::hierarchy::AppData;
-use crate::data::{AppEvent, EventUnSubscribe};
+use crate::data::{AString, AppEvent, EventUnSubscribe};
 use crate::mqtt::{init_connect, mqtt_public, mqtt_subscribe, to_unsubscribe};
 // use crate::ui::tabs::init_brokers_tabs;
 use crate::data::click_ty::ClickTy;
-use crate::data::common::{Broker, QoS, SubscribeHis, SubscribeTopic};
+use crate::data::common::{
+    Broker, MsgProperties, QoS, ScheduleConfig, SubscribeHis, SubscribeTopic,
+};
 use crate::mqtt::data::MqttPublicInput;
-use crate::mqtt::Client;
+use crate::mqtt::{benchmark, Client};
 use crate::ui::ids::{
     SCROLL_MSG_ID, SCROLL_SUBSCRIBE_ID, SELECTOR_AUTO_SCROLL, SELECTOR_TABS_SELECTED, TABS_ID, TIPS,
 };
 
 use crate::util::hint::{
-    DELETE_BROKER_SUCCESS, DELETE_SUBSCRIBE_SUCCESS, DISCONNECT_SUCCESS, PUBLISH_SUCCESS,
-    SAVE_BROKER_SUCCESS, SUBSCRIBE_SUCCESS, UNSUBSCRIBE_SUCCESS,
+    DELETE_BROKER_SUCCESS, DELETE_SUBSCRIBE_SUCCESS, DELETE_TEMPLATE_SUCCESS, DISCONNECT_SUCCESS,
+    PUBLISH_SUCCESS, SAVE_BROKER_SUCCESS, SAVE_TEMPLATE_SUCCESS, SUBSCRIBE_SUCCESS,
+    UNSUBSCRIBE_SUCCESS,
 };
 
-use anyhow::Result;
+use crate::util::replay::{read_capture, ReplayConfig};
+use anyhow::{bail, Result};
 use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
 use custom_utils::rx;
@@ -26,12 +30,19 @@ use std::collections::HashMap;
 
 use crate::config::AutoRetract;
 use druid::WidgetId;
-use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::spawn;
-use tokio::time::sleep;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, MissedTickBehavior};
+
+/// 发布后等待ack的最长时间，超过后标记为超时
+const PUBLISH_ACK_TIMEOUT: Duration = Duration::from_secs(30);
+/// 检查发布超时的间隔
+const PUBLISH_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
 
 static CLICK_INFO: AtomicUsize = AtomicUsize::new(0);
 static CLICK_LIST: AtomicUsize = AtomicUsize::new(0);
@@ -45,6 +56,11 @@ pub async fn deal_event(
 ) -> Result<()> {
     let mut mqtt_clients: HashMap<usize, Client> = HashMap::new();
     let mut click_his: Option<ClickTy> = None;
+    let mut replays: HashMap<usize, JoinHandle<()>> = HashMap::new();
+    let mut schedules: HashMap<usize, JoinHandle<()>> = HashMap::new();
+    let mut benchmarks: HashMap<usize, Arc<AtomicBool>> = HashMap::new();
+    // 等待ack的发布: trace_id -> (broker_id, 超时的时间点)
+    let mut pending_publishes: HashMap<u32, (usize, Instant)> = HashMap::new();
     let mut click_broker_info = CLICK_INFO.fetch_add(1, Relaxed);
     let mut click_broker_list = CLICK_LIST.fetch_add(1, Relaxed);
 
@@ -55,6 +71,16 @@ pub async fn deal_event(
     if tx.send(AppEvent::TouchClickBrokerList).is_err() {
         error!("fail to send event");
     }
+    let sweep_tx = tx.clone();
+    spawn(async move {
+        let mut interval = tokio::time::interval(PUBLISH_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if sweep_tx.send(AppEvent::SweepPublishTimeout).is_err() {
+                break;
+            }
+        }
+    });
     loop {
         // let event = ;
         // debug!("{:?}", event);
@@ -115,6 +141,8 @@ pub async fn deal_event(
             AppEvent::TouchConnectBrokerSelected => touch_connect_broker_selected(&event_sink),
             AppEvent::TouchSaveBroker => touch_save_broker(&event_sink),
             AppEvent::TouchRemoveSubscribeHis(id) => touch_delete_subscribe_his(&event_sink, id),
+            AppEvent::TouchSaveTemplate(id) => touch_save_template(&event_sink, id),
+            AppEvent::TouchRemoveTemplate(id, name) => touch_remove_template(&event_sink, id, name),
             AppEvent::TouchUnSubscribe {
                 broker_id,
                 trace_id,
@@ -140,13 +168,34 @@ pub async fn deal_event(
                     error!("{:?}", e);
                 }
             }
-            AppEvent::ClientReceivePublic(index, topic, payload, qos) => {
-                if let Err(e) = receive_public(&event_sink, index, topic, payload, qos).await {
+            AppEvent::ClientReceivePublic(index, topic, payload, qos, retain, properties) => {
+                if let Err(e) =
+                    receive_public(&event_sink, index, topic, payload, qos, retain, properties)
+                        .await
+                {
                     error!("{:?}", e);
                 }
             }
-            AppEvent::ClientPubAck(id, ack) => pub_ack(&event_sink, id, ack),
+            AppEvent::ClientPubAck(id, ack) => {
+                pending_publishes.remove(&ack);
+                pub_ack(&event_sink, id, ack)
+            }
+            AppEvent::ClientPublishFail(id, reason) => {
+                // 失败事件不带trace_id，只有一条发布在等待ack时才能确定是哪一条
+                let mut trace_ids = pending_publishes
+                    .iter()
+                    .filter(|(_, (broker_id, _))| *broker_id == id)
+                    .map(|(trace_id, _)| *trace_id);
+                match (trace_ids.next(), trace_ids.next()) {
+                    (Some(trace_id), None) => {
+                        pending_publishes.remove(&trace_id);
+                        pub_fail(&event_sink, id, trace_id, reason.into());
+                    }
+                    _ => error!("broker({}) publish fail: {}", id, reason),
+                }
+            }
             AppEvent::ClientSubAck(id, ack) => sub_ack(&event_sink, id, ack),
+            AppEvent::ClientSubscribeFail(id, reason) => sub_fail(&event_sink, id, reason),
             AppEvent::UpdateToSelectTabs(id) => update_to_select_tabs(&event_sink, id),
             AppEvent::TouchReConnect => {
                 if let Err(e) = touch_reconnect(&event_sink).await {
@@ -170,7 +219,12 @@ pub async fn deal_event(
             AppEvent::ClientConnectAckSuccess { broker_id, retain } => {
                 update_to_connected(&event_sink, broker_id, retain)
             } // _ => {}
-            AppEvent::ClientConnectAckFail(_id, _msg) => error!("{}", _msg.to_string()),
+            AppEvent::ClientConnectAckFail {
+                broker_id,
+                generation,
+                err,
+                initial,
+            } => connect_fail(&event_sink, broker_id, generation, err, initial),
             AppEvent::ClientDisconnect(id) => {
                 client_disconnect(&event_sink, id);
             }
@@ -181,6 +235,7 @@ pub async fn deal_event(
                 update_status_bar(&event_sink, msg);
             }
             AppEvent::TouchClearMsg(id) => clear_msg(&event_sink, id),
+            AppEvent::TouchLoadHistoryMsg(id) => load_history_msg(&event_sink, id),
 
             AppEvent::UpdateScrollSubscribeWin => scroll_subscribe_win(&event_sink).await,
             AppEvent::UpdateScrollMsgWin => scroll_msg_win(&event_sink).await,
@@ -214,6 +269,14 @@ pub async fn deal_event(
                 }
             }
             AppEvent::ToDisconnect(broker_id) => {
+                if let Some(handle) = replays.remove(&broker_id) {
+                    handle.abort();
+                    replay_stopped(&event_sink, broker_id);
+                }
+                if let Some(handle) = schedules.remove(&broker_id) {
+                    handle.abort();
+                    schedule_stopped(&event_sink, broker_id);
+                }
                 if let Err(e) = to_disconnect(&event_sink, &mut mqtt_clients, broker_id).await {
                     error!("{:?}", e);
                 }
@@ -222,8 +285,94 @@ pub async fn deal_event(
                 to_subscribe(&mqtt_clients, input).await;
             }
             AppEvent::ToPublish(input) => {
-                if let Err(e) = to_publish(&mqtt_clients, input).await {
-                    error!("{:?}", e);
+                let (broker_id, trace_id) = (input.broker_id, input.trace_id);
+                match to_publish(&mqtt_clients, input).await {
+                    Ok(_) => {
+                        pending_publishes
+                            .insert(trace_id, (broker_id, Instant::now() + PUBLISH_ACK_TIMEOUT));
+                    }
+                    Err(e) => {
+                        error!("{:?}", e);
+                        pub_fail(&event_sink, broker_id, trace_id, e.to_string().into());
+                    }
+                }
+            }
+            AppEvent::SweepPublishTimeout => {
+                let now = Instant::now();
+                let timeouts: Vec<(usize, u32)> = pending_publishes
+                    .iter()
+                    .filter(|(_, (_, deadline))| *deadline <= now)
+                    .map(|(trace_id, (broker_id, _))| (*broker_id, *trace_id))
+                    .collect();
+                if !timeouts.is_empty() {
+                    pending_publishes.retain(|_, (_, deadline)| *deadline > now);
+                    pub_timeout(&event_sink, timeouts);
+                }
+            }
+            AppEvent::TouchRetryPublish(trace_id) => retry_publish(&event_sink, trace_id),
+            AppEvent::ToReplay(broker_id, config) => {
+                if let Some(handle) = replays.remove(&broker_id) {
+                    handle.abort();
+                }
+                let event_sink = event_sink.clone();
+                replays.insert(
+                    broker_id,
+                    spawn(async move {
+                        if let Err(e) = replay(&event_sink, broker_id, config).await {
+                            error!("replay fail: {:?}", e);
+                        }
+                        replay_stopped(&event_sink, broker_id);
+                    }),
+                );
+            }
+            AppEvent::ToStopReplay(broker_id) => {
+                if let Some(handle) = replays.remove(&broker_id) {
+                    handle.abort();
+                    info!("replay stopped");
+                }
+                replay_stopped(&event_sink, broker_id);
+            }
+            AppEvent::ToStartSchedule(broker_id, config) => {
+                if let Some(handle) = schedules.remove(&broker_id) {
+                    handle.abort();
+                }
+                let event_sink = event_sink.clone();
+                schedules.insert(
+                    broker_id,
+                    spawn(async move {
+                        schedule(&event_sink, broker_id, config).await;
+                        schedule_stopped(&event_sink, broker_id);
+                    }),
+                );
+            }
+            AppEvent::ToStopSchedule(broker_id) => {
+                if let Some(handle) = schedules.remove(&broker_id) {
+                    handle.abort();
+                    info!("scheduled publish stopped");
+                }
+                schedule_stopped(&event_sink, broker_id);
+            }
+            AppEvent::ToBenchmark(broker, config) => {
+                let broker_id = broker.id;
+                let stop = Arc::new(AtomicBool::new(false));
+                if let Some(old) = benchmarks.insert(broker_id, stop.clone()) {
+                    old.store(true, Ordering::Release);
+                }
+                let event_sink = event_sink.clone();
+                spawn(async move {
+                    info!("benchmark started");
+                    let rs = benchmark::run(broker, config, stop).await;
+                    event_sink.add_idle_callback(move |data: &mut AppData| {
+                        if let Err(e) = data.benchmark_finished(broker_id, rs) {
+                            error!("benchmark fail: {:?}", e);
+                        }
+                    });
+                });
+            }
+            AppEvent::ToStopBenchmark(broker_id) => {
+                if let Some(stop) = benchmarks.remove(&broker_id) {
+                    stop.store(true, Ordering::Release);
+                    info!("benchmark stopping");
                 }
             }
             AppEvent::OtherDisplayTips => {
@@ -243,6 +392,7 @@ async fn first_click(event_sink: &druid::ExtEventSink, ty: ClickTy) {
         ClickTy::SubscribeTopic(_, _) => {}
         ClickTy::SubscribeHis(his) => click_subscribe_his(event_sink, his.clone()),
         ClickTy::ConnectTab(broker_id) => touch_click_tab(event_sink, broker_id),
+        ClickTy::TopicNode(broker_id, topic) => click_topic_node(event_sink, broker_id, topic),
     }
 }
 async fn double_click(event_sink: &druid::ExtEventSink, ty: ClickTy) -> Result<()> {
@@ -267,6 +417,7 @@ async fn double_click(event_sink: &druid::ExtEventSink, ty: ClickTy) -> Result<(
         ClickTy::ConnectTab(_) => {
             touch_reconnect(event_sink).await?;
         }
+        ClickTy::TopicNode(_, _) => {}
     }
     Ok(())
 }
@@ -326,6 +477,26 @@ fn touch_delete_subscribe_his(event_sink: &druid::ExtEventSink, id: usize) {
     });
 }
 
+fn touch_save_template(event_sink: &druid::ExtEventSink, id: usize) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Err(e) = data.touch_save_template(id) {
+            warn!("{}", e.to_string());
+        } else {
+            info!("{}", SAVE_TEMPLATE_SUCCESS);
+        }
+    });
+}
+
+fn touch_remove_template(event_sink: &druid::ExtEventSink, id: usize, name: AString) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Err(e) = data.touch_remove_template(id, name) {
+            warn!("{}", e.to_string());
+        } else {
+            info!("{}", DELETE_TEMPLATE_SUCCESS);
+        }
+    });
+}
+
 fn touch_click_tab(event_sink: &druid::ExtEventSink, broker_id: usize) {
     event_sink.add_idle_callback(move |data: &mut AppData| {
         if let Err(e) = data.touch_click_tab(broker_id) {
@@ -402,6 +573,17 @@ async fn connect(
         }
         Err(e) => {
             error!("{:?}", e);
+            if tx
+                .send(AppEvent::ClientConnectAckFail {
+                    broker_id: broker.id,
+                    generation: broker.conn_generation,
+                    err: e.to_string().into(),
+                    initial: true,
+                })
+                .is_err()
+            {
+                error!("fail to send event");
+            }
         }
     }
 }
@@ -451,9 +633,11 @@ async fn receive_public(
     topic: Arc<String>,
     payload: Arc<Bytes>,
     qos: QoS,
+    retain: bool,
+    properties: MsgProperties,
 ) -> Result<()> {
     event_sink.add_idle_callback(move |data: &mut AppData| {
-        if let Err(e) = data.receive_msg(index, topic, payload, qos) {
+        if let Err(e) = data.receive_msg(index, topic, payload, qos, retain, properties) {
             error!("{:?}", e);
         }
     });
@@ -470,6 +654,33 @@ fn pub_ack(event_sink: &druid::ExtEventSink, id: usize, trace_id: u32) {
     });
 }
 
+fn pub_fail(event_sink: &druid::ExtEventSink, id: usize, trace_id: u32, reason: AString) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Err(e) = data.pub_fail(id, trace_id, reason) {
+            error!("{}", e.to_string());
+        }
+    });
+}
+
+/// timeouts: (broker_id, trace_id)
+fn pub_timeout(event_sink: &druid::ExtEventSink, timeouts: Vec<(usize, u32)>) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        for (id, trace_id) in timeouts {
+            if let Err(e) = data.pub_timeout(id, trace_id) {
+                error!("{}", e.to_string());
+            }
+        }
+    });
+}
+
+fn retry_publish(event_sink: &druid::ExtEventSink, trace_id: u32) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Err(e) = data.retry_publish(trace_id) {
+            error!("{}", e.to_string());
+        }
+    });
+}
+
 fn sub_ack(event_sink: &druid::ExtEventSink, id: usize, ack: SubscribeAck) {
     event_sink.add_idle_callback(move |data: &mut AppData| {
         if let Err(e) = data.sub_ack(id, ack) {
@@ -479,6 +690,15 @@ fn sub_ack(event_sink: &druid::ExtEventSink, id: usize, ack: SubscribeAck) {
         }
     });
 }
+
+fn sub_fail(event_sink: &druid::ExtEventSink, id: usize, reason: String) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Err(e) = data.sub_fail(id, reason) {
+            error!("{}", e.to_string());
+        }
+    });
+}
+
 fn update_to_select_tabs(event_sink: &druid::ExtEventSink, id: usize) {
     if let Err(e) = event_sink.submit_command(SELECTOR_TABS_SELECTED, id, TABS_ID) {
         error!("{:?}", e);
@@ -501,6 +721,98 @@ fn click_subscribe_his(event_sink: &druid::ExtEventSink, his: SubscribeHis) {
     });
 }
 
+fn click_topic_node(event_sink: &druid::ExtEventSink, broker_id: usize, topic: AString) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Err(e) = data.click_topic_node(broker_id, topic) {
+            error!("{:?}", e);
+        }
+    });
+}
+
+/// 按设置的速度逐条发布记录中的消息，每条等待界面线程处理完再继续，避免堆积回调
+async fn replay(
+    event_sink: &druid::ExtEventSink,
+    broker_id: usize,
+    config: ReplayConfig,
+) -> Result<()> {
+    let msgs = read_capture(config.path.as_path())?;
+    if msgs.is_empty() {
+        bail!("no msg in {}", config.path.display());
+    }
+    let mut logged_at = Instant::now();
+    loop {
+        let mut last = None;
+        for (index, msg) in msgs.iter().enumerate() {
+            if let Some(delay) = config.delay(last, msg.timestamp) {
+                sleep(delay).await;
+            }
+            last = Some(msg.timestamp);
+            let msg = config.rewrite(msg.clone());
+            let (done_tx, done_rx) = oneshot::channel();
+            event_sink.add_idle_callback(move |data: &mut AppData| {
+                if let Err(e) = data.replay_publish(broker_id, msg) {
+                    error!("{:?}", e);
+                }
+                let _ = done_tx.send(());
+            });
+            if done_rx.await.is_err() {
+                bail!("replay callback dropped");
+            }
+            // 进度每秒最多输出一次
+            if logged_at.elapsed() >= Duration::from_secs(1) || index + 1 == msgs.len() {
+                info!("replay: {}/{}", index + 1, msgs.len());
+                logged_at = Instant::now();
+            }
+        }
+        if !config.looped {
+            break;
+        }
+    }
+    info!("replay finished");
+    Ok(())
+}
+
+/// 按间隔发布，达到次数后结束。每条等待界面线程处理完再继续，发布失败时停止
+async fn schedule(event_sink: &druid::ExtEventSink, broker_id: usize, config: ScheduleConfig) {
+    let mut interval = tokio::time::interval(config.interval);
+    // 界面线程处理不过来时顺延，而不是补发错过的
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut sent = 0;
+    while !matches!(config.count, Some(count) if sent >= count) {
+        interval.tick().await;
+        sent += 1;
+        let input = config.input.clone();
+        let (done_tx, done_rx) = oneshot::channel();
+        event_sink.add_idle_callback(move |data: &mut AppData| {
+            let rs = data.schedule_publish(broker_id, input);
+            if let Err(e) = &rs {
+                error!("scheduled publish fail: {:?}", e);
+            }
+            let _ = done_tx.send(rs.is_ok());
+        });
+        if !matches!(done_rx.await, Ok(true)) {
+            break;
+        }
+    }
+    info!("scheduled publish finished: {}", sent);
+}
+
+fn schedule_stopped(event_sink: &druid::ExtEventSink, broker_id: usize) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Ok(broker) = data.find_mut_broker_by_id(broker_id) {
+            broker.schedule.running = false;
+        }
+    });
+}
+
+fn replay_stopped(event_sink: &druid::ExtEventSink, broker_id: usize) {
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Ok(broker) = data.find_mut_broker_by_id(broker_id) {
+            broker.replay.running = false;
+        }
+    });
+}
+
 async fn touch_reconnect(event_sink: &druid::ExtEventSink) -> Result<()> {
     // if let Some(client) = mqtt_clients.remove(&id) {
     //     client.disconnect().await?;
@@ -573,6 +885,29 @@ fn clear_msg(event_sink: &druid::ExtEventSink, id: usize) {
     });
 }
 
+fn load_history_msg(event_sink: &druid::ExtEventSink, id: usize) {
+    event_sink.add_idle_callback(move |data: &mut AppData| match data.load_history_msg(id) {
+        Ok(0) => info!("no more history msg!"),
+        Ok(len) => info!("load {} history msg success!", len),
+        Err(e) => error!("{:?}", e),
+    });
+}
+
+fn connect_fail(
+    event_sink: &druid::ExtEventSink,
+    id: usize,
+    generation: u64,
+    msg: Arc<String>,
+    initial: bool,
+) {
+    error!("connect fail: {}", msg);
+    event_sink.add_idle_callback(move |data: &mut AppData| {
+        if let Err(e) = data.connect_fail(id, generation, msg, initial) {
+            error!("{:?}", e);
+        }
+    });
+}
+
 fn client_disconnect(event_sink: &druid::ExtEventSink, id: usize) {
     event_sink.add_idle_callback(move |data: &mut AppData| {
         if let Err(e) = data.client_disconnect(id) {
@@ -0,0 +1,342 @@
+//! 压测：多个连接按目标速率发布，另一个连接订阅并统计端到端延迟与丢失
+use crate::data::common::{Broker, Id, QoS};
+use crate::data::AppEvent;
+use crate::mqtt::{init_connect, Client};
+use crate::util::general_id;
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use crossbeam_channel::{Receiver, Sender};
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::{spawn_blocking, JoinHandle};
+use tokio::time::sleep;
+
+/// 发布结束后等待订阅端收完消息的最长时间
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(3);
+/// payload的前8个字节为发送时间(微秒)
+const TIMESTAMP_LEN: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub clients: usize,
+    /// 每个连接每秒发布的消息数
+    pub rate: f64,
+    pub payload_size: usize,
+    pub qos: QoS,
+    pub duration: Duration,
+    /// 第n个连接发布到{topic}/{n}，订阅端订阅{topic}/#
+    pub topic: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BenchReport {
+    pub clients: usize,
+    pub connected: u64,
+    pub qos: u8,
+    pub payload_size: usize,
+    /// 秒
+    pub elapsed: f64,
+    pub sent: u64,
+    pub acked: u64,
+    pub received: u64,
+    pub lost: u64,
+    pub connect_errors: u64,
+    pub publish_errors: u64,
+    pub connection_errors: u64,
+    /// 每秒发布、收到的消息数
+    pub send_throughput: f64,
+    pub receive_throughput: f64,
+    /// 毫秒
+    pub latency_p50: f64,
+    pub latency_p95: f64,
+    pub latency_p99: f64,
+    pub latency_max: f64,
+}
+
+impl BenchReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "connected: {}/{} sent: {} acked: {} received: {} lost: {} errors(connect/publish/connection): {}/{}/{}\n\
+             throughput: {:.1} msg/s sent, {:.1} msg/s received\n\
+             latency(ms): p50 {:.2} p95 {:.2} p99 {:.2} max {:.2}",
+            self.connected,
+            self.clients,
+            self.sent,
+            self.acked,
+            self.received,
+            self.lost,
+            self.connect_errors,
+            self.publish_errors,
+            self.connection_errors,
+            self.send_throughput,
+            self.receive_throughput,
+            self.latency_p50,
+            self.latency_p95,
+            self.latency_p99,
+            self.latency_max
+        )
+    }
+}
+
+/// 由各连接的事件汇总的统计
+#[derive(Default)]
+struct Stats {
+    connected: AtomicU64,
+    connect_errors: AtomicU64,
+    subscribed: AtomicBool,
+    acked: AtomicU64,
+    received: AtomicU64,
+    connection_errors: AtomicU64,
+    /// 微秒
+    latencies: Mutex<Vec<u64>>,
+}
+
+/// 运行压测，stop被置位时提前结束并返回已有的统计
+pub async fn run(
+    broker: Broker,
+    config: BenchConfig,
+    stop: Arc<AtomicBool>,
+) -> Result<BenchReport> {
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let start = Instant::now();
+    let stats = Arc::new(Stats::default());
+    let done = Arc::new(AtomicBool::new(false));
+    let subscriber_id = config.clients;
+    let collector = collect(rx, subscriber_id, start, stats.clone(), done.clone());
+    let conn_timeout = Duration::from_secs(broker.option.conn_timeout);
+
+    let subscriber = connect(&broker, subscriber_id, tx.clone()).await?;
+    subscriber
+        .to_subscribe_with_trace_id(
+            format!("{}/#", config.topic),
+            config.qos.clone().into(),
+            Id::to_id(),
+        )
+        .await?;
+    if !wait_until(conn_timeout, || stats.subscribed.load(Ordering::Acquire)).await {
+        done.store(true, Ordering::Release);
+        let _ = subscriber.disconnect().await;
+        bail!(
+            "subscriber could not subscribe in {}s",
+            conn_timeout.as_secs()
+        );
+    }
+
+    let mut clients = Vec::with_capacity(config.clients);
+    for index in 0..config.clients {
+        match connect(&broker, index, tx.clone()).await {
+            Ok(client) => clients.push((index, client)),
+            Err(e) => {
+                warn!("benchmark client {} fail to connect: {:?}", index, e);
+                stats.connect_errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+    let expected = clients.len() as u64 + 1;
+    wait_until(conn_timeout, || {
+        stats.connected.load(Ordering::Acquire) + stats.connect_errors.load(Ordering::Acquire)
+            >= expected
+    })
+    .await;
+    info!(
+        "benchmark: {}/{} clients connected",
+        stats.connected.load(Ordering::Acquire).saturating_sub(1),
+        config.clients
+    );
+
+    let publish_start = Instant::now();
+    let sent = Arc::new(AtomicU64::new(0));
+    let publish_errors = Arc::new(AtomicU64::new(0));
+    let publishers: Vec<JoinHandle<Client>> = clients
+        .into_iter()
+        .map(|(index, client)| {
+            tokio::spawn(publish(
+                client,
+                index,
+                config.clone(),
+                start,
+                stop.clone(),
+                sent.clone(),
+                publish_errors.clone(),
+            ))
+        })
+        .collect();
+    while publishers.iter().any(|x| !x.is_finished()) {
+        sleep(Duration::from_secs(1)).await;
+        info!(
+            "benchmark: sent {} received {}",
+            sent.load(Ordering::Relaxed),
+            stats.received.load(Ordering::Relaxed)
+        );
+    }
+    let publish_elapsed = publish_start.elapsed();
+    let total = sent.load(Ordering::Acquire);
+    wait_until(DRAIN_TIMEOUT, || {
+        stats.received.load(Ordering::Acquire) >= total
+    })
+    .await;
+    done.store(true, Ordering::Release);
+    collector.await?;
+
+    for publisher in publishers {
+        if let Ok(client) = publisher.await {
+            let _ = client.disconnect().await;
+        }
+    }
+    let _ = subscriber.disconnect().await;
+
+    let mut latencies = std::mem::take(&mut *stats.latencies.lock().unwrap());
+    latencies.sort_unstable();
+    let received = stats.received.load(Ordering::Acquire);
+    let elapsed = publish_elapsed.as_secs_f64().max(f64::EPSILON);
+    Ok(BenchReport {
+        clients: config.clients,
+        connected: stats.connected.load(Ordering::Acquire).saturating_sub(1),
+        qos: config.qos.clone() as u8,
+        payload_size: config.payload_size.max(TIMESTAMP_LEN),
+        elapsed: publish_elapsed.as_secs_f64(),
+        sent: total,
+        acked: stats.acked.load(Ordering::Acquire),
+        received,
+        lost: total.saturating_sub(received),
+        connect_errors: stats.connect_errors.load(Ordering::Acquire),
+        publish_errors: publish_errors.load(Ordering::Acquire),
+        connection_errors: stats.connection_errors.load(Ordering::Acquire),
+        send_throughput: total as f64 / elapsed,
+        receive_throughput: received as f64 / elapsed,
+        latency_p50: percentile(&latencies, 50.0),
+        latency_p95: percentile(&latencies, 95.0),
+        latency_p99: percentile(&latencies, 99.0),
+        latency_max: latencies
+            .last()
+            .map(|x| *x as f64 / 1000.0)
+            .unwrap_or_default(),
+    })
+}
+
+/// 以broker的配置建立连接，使用生成的client id
+async fn connect(broker: &Broker, index: usize, tx: Sender<AppEvent>) -> Result<Client> {
+    let mut broker = broker.clone();
+    broker.id = index;
+    broker.client_id = Arc::new(general_id());
+    broker.auto_connect = false;
+    init_connect(broker, tx).await
+}
+
+async fn publish(
+    client: Client,
+    index: usize,
+    config: BenchConfig,
+    start: Instant,
+    stop: Arc<AtomicBool>,
+    sent: Arc<AtomicU64>,
+    errors: Arc<AtomicU64>,
+) -> Client {
+    let topic = Arc::new(format!("{}/{}", config.topic, index));
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / config.rate));
+    let publish_start = Instant::now();
+    while publish_start.elapsed() < config.duration && !stop.load(Ordering::Acquire) {
+        interval.tick().await;
+        let mut payload = vec![0u8; config.payload_size.max(TIMESTAMP_LEN)];
+        payload[..TIMESTAMP_LEN]
+            .copy_from_slice(&(start.elapsed().as_micros() as u64).to_be_bytes());
+        match client
+            .publish_with_trace_id(
+                topic.clone(),
+                config.qos.clone().into(),
+                Bytes::from(payload),
+                false,
+                Id::to_id(),
+            )
+            .await
+        {
+            Ok(_) => sent.fetch_add(1, Ordering::Relaxed),
+            Err(_) => errors.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+    client
+}
+
+/// 在阻塞线程中处理各连接的事件，直到done被置位
+fn collect(
+    rx: Receiver<AppEvent>,
+    subscriber_id: usize,
+    start: Instant,
+    stats: Arc<Stats>,
+    done: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    spawn_blocking(move || {
+        while !done.load(Ordering::Acquire) {
+            let Ok(event) = rx.recv_timeout(Duration::from_millis(100)) else {
+                continue;
+            };
+            match event {
+                AppEvent::ClientConnectAckSuccess { .. } => {
+                    stats.connected.fetch_add(1, Ordering::AcqRel);
+                }
+                AppEvent::ClientConnectAckFail { .. } => {
+                    stats.connect_errors.fetch_add(1, Ordering::AcqRel);
+                }
+                AppEvent::ClientSubAck(id, _) if id == subscriber_id => {
+                    stats.subscribed.store(true, Ordering::Release);
+                }
+                AppEvent::ClientPubAck(..) => {
+                    stats.acked.fetch_add(1, Ordering::Relaxed);
+                }
+                AppEvent::ClientReceivePublic(id, _, payload, ..) if id == subscriber_id => {
+                    if let Some(timestamp) = payload
+                        .get(..TIMESTAMP_LEN)
+                        .and_then(|x| <[u8; TIMESTAMP_LEN]>::try_from(x).ok())
+                    {
+                        let now = start.elapsed().as_micros() as u64;
+                        let latency = now.saturating_sub(u64::from_be_bytes(timestamp));
+                        stats.latencies.lock().unwrap().push(latency);
+                    }
+                    stats.received.fetch_add(1, Ordering::AcqRel);
+                }
+                AppEvent::ClientConnectedErr(..) | AppEvent::ClientDisconnect(_) => {
+                    stats.connection_errors.fetch_add(1, Ordering::Relaxed);
+                }
+                _ => {}
+            }
+        }
+    })
+}
+
+async fn wait_until(timeout: Duration, f: impl Fn() -> bool) -> bool {
+    let start = Instant::now();
+    while !f() {
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    true
+}
+
+/// sorted为升序的微秒数，返回毫秒
+fn percentile(sorted: &[u64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1] as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::percentile;
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[], 50.0), 0.0);
+        let latencies: Vec<u64> = (1..=100).map(|x| x * 1000).collect();
+        assert_eq!(percentile(&latencies, 50.0), 50.0);
+        assert_eq!(percentile(&latencies, 95.0), 95.0);
+        assert_eq!(percentile(&latencies, 99.0), 99.0);
+        assert_eq!(percentile(&[3000], 99.0), 3.0);
+    }
+}
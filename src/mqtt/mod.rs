@@ -1,20 +1,27 @@
+pub mod benchmark;
 pub mod data;
+pub mod topic;
 
-use crate::data::common::SignedTy;
-use crate::data::common::{Broker, Protocol};
+use crate::data::common::{Broker, ConnectOption, MsgProperties, Protocol};
+use crate::data::common::{ClientCertTy, ClientKeyTy, SignedTy};
 use crate::data::AppEvent;
 use crate::mqtt::data::{MqttPublicInput, MqttSubscribeInput};
+use crate::util::cert::pkcs12_to_pem;
+use crate::util::consts::MIN_KEEP_ALIVE;
 
 use anyhow::{bail, Result};
 use crossbeam_channel::Sender;
 use druid::piet::TextStorage;
 use log::{debug, error, info};
-use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
 
-use for_mqtt_client::protocol::packet::Publish;
+use for_mqtt_client::protocol::packet::{Publish, SubscribeReasonCode};
 use for_mqtt_client::protocol::MqttOptions;
-use for_mqtt_client::tls::TlsConfig;
+use for_mqtt_client::tls::{CertificateFile, PrivateKeyFile, TlsConfig};
 use for_mqtt_client::MqttEvent;
 pub use for_mqtt_client::{
     protocol::packet::{PubAck, SubAck},
@@ -31,24 +38,53 @@ pub async fn init_connect(broker: Broker, tx: Sender<AppEvent>) -> Result<Client
         mqttoptions =
             mqttoptions.set_credentials(broker.user_name.clone(), broker.password.clone());
     }
-    let some = serde_json::from_str(broker.params.as_str())?;
-    mqttoptions = update_tls_option(update_option(mqttoptions.clone(), some), broker.clone());
+    let conn_timeout = Duration::from_secs(broker.option.conn_timeout);
+    mqttoptions = update_tls_option(
+        update_option(mqttoptions.clone(), &broker.option),
+        broker.clone(),
+    )?;
     if broker.auto_connect {
         mqttoptions = mqttoptions.auto_reconnect();
     }
     debug!("{:?}", mqttoptions);
-    let (client, mut eventloop) = match broker.protocol {
-        Protocol::V4 => mqttoptions.connect_to_v4().await?,
-        Protocol::V5 => mqttoptions.connect_to_v5().await?,
+    let connect = async {
+        match broker.protocol {
+            Protocol::V4 => mqttoptions.connect_to_v4().await,
+            Protocol::V5 => mqttoptions.connect_to_v5().await,
+        }
+    };
+    let (client, mut eventloop) = match timeout(conn_timeout, connect).await {
+        Ok(rs) => rs?,
+        Err(_) => bail!("connect timeout({}s)", conn_timeout.as_secs()),
     };
     // let mut eventloop = client.init_receiver();
     let id = broker.id;
+    let generation = broker.conn_generation;
+    // 在conn_timeout内未收到connack，则视为连接失败
+    let connack = Arc::new(AtomicBool::new(false));
+    let connack_timer = connack.clone();
+    let tx_timer = tx.clone();
+    tokio::spawn(async move {
+        sleep(conn_timeout).await;
+        if !connack_timer.load(Ordering::Acquire) {
+            send_event(
+                tx_timer,
+                AppEvent::ClientConnectAckFail {
+                    broker_id: id,
+                    generation,
+                    err: format!("connect timeout({}s)", conn_timeout.as_secs()).into(),
+                    initial: true,
+                },
+            );
+        }
+    });
     tokio::spawn(async move {
         while let Ok(event) = eventloop.recv().await {
             let tx = tx.clone();
             // debug!("{:?}", event);
             match event.as_ref() {
                 MqttEvent::ConnectSuccess(retain) => {
+                    connack.store(true, Ordering::Release);
                     send_event(
                         tx,
                         AppEvent::ClientConnectAckSuccess {
@@ -58,9 +94,15 @@ pub async fn init_connect(broker: Broker, tx: Sender<AppEvent>) -> Result<Client
                     );
                 }
                 MqttEvent::ConnectFail(err) => {
+                    connack.store(true, Ordering::Release);
                     send_event(
                         tx,
-                        AppEvent::ClientConnectAckFail(id, format!("{:?}", err).into()),
+                        AppEvent::ClientConnectAckFail {
+                            broker_id: id,
+                            generation,
+                            err: format!("{:?}", err).into(),
+                            initial: false,
+                        },
                     );
                 }
                 MqttEvent::PublishSuccess(packet_id) => {
@@ -75,10 +117,12 @@ pub async fn init_connect(broker: Broker, tx: Sender<AppEvent>) -> Result<Client
                 MqttEvent::Publish(msg) => {
                     let Publish {
                         qos,
+                        retain,
                         topic,
                         payload,
                         ..
                     } = msg;
+                    let properties = to_msg_properties(msg);
                     debug!("recv publish: {} payload len = {}", topic, payload.len());
                     send_event(
                         tx,
@@ -87,14 +131,16 @@ pub async fn init_connect(broker: Broker, tx: Sender<AppEvent>) -> Result<Client
                             topic.clone(),
                             payload.clone(),
                             qos.clone().into(),
+                            *retain,
+                            properties,
                         ),
                     );
                 }
                 MqttEvent::PublishFail(reason) => {
-                    error!("{}", reason);
+                    send_event(tx, AppEvent::ClientPublishFail(id, reason.clone()));
                 }
                 MqttEvent::SubscribeFail(reason) => {
-                    error!("{}", reason);
+                    send_event(tx, AppEvent::ClientSubscribeFail(id, reason.clone()));
                 }
                 MqttEvent::ConnectedErr(reason) => {
                     error!("{}", reason);
@@ -144,6 +190,7 @@ pub async fn to_unsubscribe(
     Ok(client.unsubscribe(topic).await?)
 }
 
+/// for-mqtt-client的publish不能携带v5 properties，发送的消息不带properties
 pub async fn mqtt_public(
     index: usize,
     input: MqttPublicInput,
@@ -163,68 +210,128 @@ pub async fn mqtt_public(
         .await?)
 }
 
-fn update_option(option: MqttOptions, some: SomeMqttOption) -> MqttOptions {
-    let SomeMqttOption {
+/// 接收到的v5 publish properties
+fn to_msg_properties(publish: &Publish) -> MsgProperties {
+    let Some(val) = publish.properties.as_ref() else {
+        return MsgProperties::default();
+    };
+    MsgProperties {
+        payload_format_indicator: val.payload_format_indicator == Some(1),
+        message_expiry_interval: val.message_expiry_interval.unwrap_or_default(),
+        content_type: Arc::new(val.content_type.clone().unwrap_or_default()),
+        response_topic: Arc::new(val.response_topic.clone().unwrap_or_default()),
+        correlation_data: Arc::new(
+            val.correlation_data
+                .as_ref()
+                .map(|x| String::from_utf8_lossy(x.as_ref()).to_string())
+                .unwrap_or_default(),
+        ),
+        user_properties: MsgProperties::user_properties_from_vec(&val.user_properties),
+    }
+}
+
+fn update_option(option: MqttOptions, some: &ConnectOption) -> MqttOptions {
+    let ConnectOption {
         keep_alive,
         clean_session,
         max_incoming_packet_size,
         max_outgoing_packet_size,
-        inflight: _,
         conn_timeout: _,
-    } = some;
+    } = some.clone();
+    // set_keep_alive要求至少为5，否则panic；旧数据可能小于5
     option
         .set_clean_session(clean_session)
         .set_max_packet_size(max_incoming_packet_size, max_outgoing_packet_size)
-        .set_keep_alive(keep_alive)
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct SomeMqttOption {
-    // seconds
-    keep_alive: u16,
-    clean_session: bool,
-    max_incoming_packet_size: usize,
-    max_outgoing_packet_size: usize,
-    inflight: u16,
-    // seconds
-    conn_timeout: u64,
+        .set_keep_alive(keep_alive.max(MIN_KEEP_ALIVE))
 }
 
-impl Default for SomeMqttOption {
-    fn default() -> Self {
-        Self {
-            keep_alive: 60,
-            clean_session: true,
-            max_incoming_packet_size: 10 * 1024,
-            max_outgoing_packet_size: 10 * 1024,
-            inflight: 100,
-            conn_timeout: 5,
-        }
-    }
-}
-
-fn update_tls_option(option: MqttOptions, value: Broker) -> MqttOptions {
+fn update_tls_option(option: MqttOptions, value: Broker) -> Result<MqttOptions> {
     if value.tls {
-        let tls_config = match value.signed_ty {
+        let mut tls_config = match value.signed_ty {
             SignedTy::Ca => TlsConfig::default(),
             SignedTy::SelfSigned => {
                 TlsConfig::default().set_server_ca_pem_file(value.self_signed_ca.as_str().into())
             }
             SignedTy::Insecurity => TlsConfig::default().insecurity(),
         };
-        option.set_tls(tls_config)
+        if value.client_cert.enable {
+            let cert = &value.client_cert;
+            let (cert, key) = match cert.ty {
+                ClientCertTy::Pem => {
+                    let key = match cert.key_ty {
+                        ClientKeyTy::Pkcs8 => PrivateKeyFile::Pkcs8(cert.key.as_str().into()),
+                        ClientKeyTy::Rsa => PrivateKeyFile::Rsa(cert.key.as_str().into()),
+                    };
+                    (cert.cert.as_str().into(), key)
+                }
+                ClientCertTy::Pkcs12 => {
+                    let (cert, key) = pkcs12_to_pem(
+                        value.id,
+                        cert.pkcs12.as_str(),
+                        cert.pkcs12_password.as_str(),
+                    )?;
+                    (cert, PrivateKeyFile::Pkcs8(key))
+                }
+            };
+            tls_config = tls_config.verify_client(CertificateFile::Pem(cert), key);
+        }
+        Ok(option.set_tls(tls_config))
     } else {
-        option
+        Ok(option)
     }
 }
 
+/// 订阅结果的原因码和可读的原因，如 0x87 not authorized
+pub fn subscribe_fail_reason(code: &SubscribeReasonCode) -> String {
+    let (code, reason) = match code {
+        SubscribeReasonCode::QoS0 => (0x00, "granted qos 0"),
+        SubscribeReasonCode::QoS1 => (0x01, "granted qos 1"),
+        SubscribeReasonCode::QoS2 => (0x02, "granted qos 2"),
+        SubscribeReasonCode::Failure => (0x80, "failure"),
+        SubscribeReasonCode::Unspecified => (0x80, "unspecified error"),
+        SubscribeReasonCode::ImplementationSpecific => (0x83, "implementation specific error"),
+        SubscribeReasonCode::NotAuthorized => (0x87, "not authorized"),
+        SubscribeReasonCode::TopicFilterInvalid => (0x8F, "topic filter invalid"),
+        SubscribeReasonCode::PkidInUse => (0x91, "packet identifier in use"),
+        SubscribeReasonCode::QuotaExceeded => (0x97, "quota exceeded"),
+        SubscribeReasonCode::SharedSubscriptionsNotSupported => {
+            (0x9E, "shared subscriptions not supported")
+        }
+        SubscribeReasonCode::SubscriptionIdNotSupported => {
+            (0xA1, "subscription identifiers not supported")
+        }
+        SubscribeReasonCode::WildcardSubscriptionsNotSupported => {
+            (0xA2, "wildcard subscriptions not supported")
+        }
+    };
+    format!("0x{:02X} {}", code, reason)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::mqtt::SomeMqttOption;
+    use super::subscribe_fail_reason;
+    use crate::data::common::ConnectOption;
+    use for_mqtt_client::protocol::packet::SubscribeReasonCode;
+
+    #[test]
+    fn test_subscribe_fail_reason() {
+        assert_eq!(
+            subscribe_fail_reason(&SubscribeReasonCode::NotAuthorized),
+            "0x87 not authorized"
+        );
+        assert_eq!(
+            subscribe_fail_reason(&SubscribeReasonCode::SharedSubscriptionsNotSupported),
+            "0x9E shared subscriptions not supported"
+        );
+        assert_eq!(
+            subscribe_fail_reason(&SubscribeReasonCode::Failure),
+            "0x80 failure"
+        );
+    }
 
     #[test]
     fn test_option() {
-        let option = SomeMqttOption::default();
+        let option = ConnectOption::default();
         println!("{}", serde_json::to_string(&option).unwrap());
 
         let option_str = r#"{
@@ -236,7 +343,8 @@ mod test {
 	"conn_timeout": 5
 }
         "#;
-        let option: SomeMqttOption = serde_json::from_str(option_str).unwrap();
+        let option: ConnectOption = serde_json::from_str(option_str).unwrap();
         println!("{:?}", option);
+        assert_eq!(option, ConnectOption::default());
     }
 }
@@ -0,0 +1,151 @@
+//! topic filter的匹配，参照MQTT规范 4.7 Topic Names and Topic Filters
+
+const SHARE_PREFIX: &str = "$share/";
+/// emqx的共享订阅前缀
+const QUEUE_PREFIX: &str = "$queue/";
+
+/// 判断topic是否被topic filter匹配。filter不合法时视为不匹配
+pub fn match_topic(filter: &str, topic: &str) -> bool {
+    let Some(filter) = strip_shared(filter) else {
+        return false;
+    };
+    if !is_valid_filter(filter) || !is_valid_topic(topic) {
+        return false;
+    }
+    // 以$开头的topic不能被以通配符开头的filter匹配
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic.split('/');
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(filter_level), Some(topic_level)) => {
+                if filter_level != topic_level {
+                    return false;
+                }
+            }
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// 去除共享订阅的前缀：$share/{group}/{filter}、$queue/{filter}
+pub fn strip_shared(filter: &str) -> Option<&str> {
+    if let Some(rest) = filter.strip_prefix(SHARE_PREFIX) {
+        let (group, filter) = rest.split_once('/')?;
+        if group.is_empty() || group.contains(['+', '#']) || filter.is_empty() {
+            return None;
+        }
+        Some(filter)
+    } else if let Some(filter) = filter.strip_prefix(QUEUE_PREFIX) {
+        if filter.is_empty() {
+            return None;
+        }
+        Some(filter)
+    } else {
+        Some(filter)
+    }
+}
+
+/// `#`只能单独出现在最后一级，`+`只能单独占据一级
+pub fn is_valid_filter(filter: &str) -> bool {
+    if filter.is_empty() {
+        return false;
+    }
+    let mut levels = filter.split('/').peekable();
+    while let Some(level) = levels.next() {
+        match level {
+            "#" => return levels.peek().is_none(),
+            "+" => {}
+            _ => {
+                if level.contains(['+', '#']) {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+pub fn is_valid_topic(topic: &str) -> bool {
+    !topic.is_empty() && !topic.contains(['+', '#'])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mqtt::topic::{is_valid_filter, match_topic};
+
+    #[test]
+    fn test_multi_level_wildcard() {
+        assert!(match_topic(
+            "sport/tennis/player1/#",
+            "sport/tennis/player1"
+        ));
+        assert!(match_topic(
+            "sport/tennis/player1/#",
+            "sport/tennis/player1/ranking"
+        ));
+        assert!(match_topic(
+            "sport/tennis/player1/#",
+            "sport/tennis/player1/score/wimbledon"
+        ));
+        assert!(match_topic("sport/#", "sport"));
+        assert!(match_topic("#", "sport/tennis"));
+        assert!(match_topic("+/status/#", "device1/status"));
+        assert!(match_topic("+/status/#", "device1/status/online"));
+        assert!(!match_topic("+/status/#", "device1/state/online"));
+        assert!(!match_topic("sport/tennis/#", "sport/tennisplayer1"));
+    }
+
+    #[test]
+    fn test_single_level_wildcard() {
+        assert!(match_topic("sport/tennis/+", "sport/tennis/player1"));
+        assert!(match_topic("sport/tennis/+", "sport/tennis/player2"));
+        assert!(!match_topic(
+            "sport/tennis/+",
+            "sport/tennis/player1/ranking"
+        ));
+        assert!(!match_topic("sport/+", "sport"));
+        assert!(match_topic("sport/+", "sport/"));
+        assert!(match_topic("+/+", "/finance"));
+        assert!(match_topic("/+", "/finance"));
+        assert!(!match_topic("+", "/finance"));
+        assert!(match_topic("a/+/c", "a/b/c"));
+        assert!(!match_topic("a/+/c", "a/b/d"));
+        assert!(!match_topic("a/+/c", "a/b/b/c"));
+        assert!(match_topic("+/+/+", "a/b/c"));
+    }
+
+    #[test]
+    fn test_system_topic() {
+        assert!(!match_topic("#", "$SYS/monitor/Clients"));
+        assert!(!match_topic("+/monitor/Clients", "$SYS/monitor/Clients"));
+        assert!(match_topic("$SYS/#", "$SYS/monitor/Clients"));
+        assert!(match_topic("$SYS/monitor/+", "$SYS/monitor/Clients"));
+    }
+
+    #[test]
+    fn test_shared_subscription() {
+        assert!(match_topic("$share/group/sport/+", "sport/tennis"));
+        assert!(match_topic("$share/group/#", "sport/tennis"));
+        assert!(match_topic("$queue/sport/#", "sport/tennis"));
+        assert!(!match_topic("$share/group", "group"));
+        assert!(!match_topic("$share//sport", "sport"));
+    }
+
+    #[test]
+    fn test_invalid_filter() {
+        assert!(is_valid_filter("sport/tennis/#"));
+        assert!(is_valid_filter("+"));
+        assert!(is_valid_filter("+/tennis/#"));
+        assert!(!is_valid_filter("sport/tennis#"));
+        assert!(!is_valid_filter("sport/tennis/#/ranking"));
+        assert!(!is_valid_filter("sport+"));
+        assert!(!match_topic("sport+", "sport+"));
+        assert!(!match_topic("sport/#", "sport/#"));
+    }
+}
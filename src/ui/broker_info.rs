@@ -1,18 +1,23 @@
-use crate::data::common::{Broker, Protocol, SignedTy};
+use crate::data::common::{
+    Broker, CertFileTy, ClientCert, ClientCertTy, ClientKeyTy, ConnectOption, MsgRetention,
+    Protocol, SignedTy,
+};
 use crate::data::lens::PortLens;
 use crate::data::AppEvent;
 use crate::ui::common::{error_display_widget, label_static, BUTTON_PADDING, TEXTBOX_WIDTH};
-use crate::ui::formatter::{check_port, MustInput};
+use crate::ui::formatter::{check_keep_alive, check_number, check_port, KeepAliveInput, MustInput};
 use crate::ui::ids::{
-    TextBoxErrorDelegate, ID_ADDR, ID_BUTTON_CONNECT, ID_BUTTON_RECONNECT, ID_PORT,
-    SELF_SIGNED_FILE,
+    TextBoxErrorDelegate, CERT_FILE, ID_ADDR, ID_BUTTON_CONNECT, ID_BUTTON_RECONNECT,
+    ID_CONN_TIMEOUT, ID_KEEP_ALIVE, ID_MAX_INCOMING_SIZE, ID_MAX_OUTGOING_SIZE, ID_PORT,
+    ID_RETENTION_AGE, ID_RETENTION_BYTES, ID_RETENTION_COUNT,
 };
 
 use crate::data::localized::Locale;
 use crossbeam_channel::Sender;
-use druid::widget::{Button, Either, Flex, RadioGroup, Switch, TextBox};
-use druid::WidgetExt;
-use druid::{Env, FileDialogOptions, FileSpec, UnitPoint, Widget};
+use druid::text::Formatter;
+use druid::widget::{Button, Either, Flex, RadioGroup, Scroll, Switch, TextBox};
+use druid::{Data, Env, FileDialogOptions, FileSpec, Lens, UnitPoint, Widget, WidgetId};
+use druid::{LensExt, WidgetExt};
 use log::error;
 
 pub fn display_broker(id: usize, tx: Sender<AppEvent>, locale: Locale) -> impl Widget<Broker> {
@@ -23,7 +28,7 @@ pub fn display_broker(id: usize, tx: Sender<AppEvent>, locale: Locale) -> impl W
     let save_tx_1 = tx.clone();
     let reconnect_tx_1 = tx.clone();
 
-    Flex::column()
+    let content = Flex::column()
         .with_child(
             Flex::row()
                 .with_child(label_static("name", UnitPoint::RIGHT))
@@ -79,6 +84,8 @@ pub fn display_broker(id: usize, tx: Sender<AppEvent>, locale: Locale) -> impl W
                 .align_left(),
         )
         .with_child(display_tls(id, locale.clone()))
+        .with_child(display_retention())
+        .with_child(display_option())
         .with_child(Either::new(
             move |data: &Broker, _: &Env| data.tab_status.connected,
             Flex::row()
@@ -90,25 +97,8 @@ pub fn display_broker(id: usize, tx: Sender<AppEvent>, locale: Locale) -> impl W
                 .with_child(save_button(save_tx_1, locale.clone()))
                 .with_child(connect_button(connect_tx_1, locale.clone()))
                 .align_left(),
-        ))
-        .with_flex_child(
-            Flex::row()
-                .with_child(label_static("params", UnitPoint::RIGHT).expand_height())
-                .with_flex_child(
-                    TextBox::multiline()
-                        // .background(B_BOXTEXT)
-                        // .with_placeholder("Multi")
-                        .lens(Broker::params)
-                        .expand_width()
-                        .expand_height()
-                        .align_left(),
-                    1.0,
-                )
-                .expand_height(),
-            1.0,
-        )
-        .padding(5.0)
-        .expand_height()
+        ));
+    Scroll::new(content.padding(5.0)).vertical().expand_height()
 }
 
 pub fn display_tls(id: usize, locale: Locale) -> impl Widget<Broker> {
@@ -121,7 +111,8 @@ pub fn display_tls(id: usize, locale: Locale) -> impl Widget<Broker> {
                     .with_child(Switch::new().lens(Broker::tls))
                     .align_left(),
             )
-            .with_child(display_signed_ty(id, locale))
+            .with_child(display_signed_ty(id, locale.clone()))
+            .with_child(display_client_cert(id, locale))
             .align_left(),
         Flex::row()
             .with_child(label_static("tls", UnitPoint::RIGHT))
@@ -130,6 +121,93 @@ pub fn display_tls(id: usize, locale: Locale) -> impl Widget<Broker> {
     )
 }
 
+pub fn display_retention() -> impl Widget<Broker> {
+    Flex::column()
+        .with_child(number_input(
+            "max msgs",
+            ID_RETENTION_COUNT,
+            Broker::retention.then(MsgRetention::max_count),
+        ))
+        .with_child(number_input(
+            "max bytes",
+            ID_RETENTION_BYTES,
+            Broker::retention.then(MsgRetention::max_bytes),
+        ))
+        .with_child(number_input(
+            "max age(s)",
+            ID_RETENTION_AGE,
+            Broker::retention.then(MsgRetention::max_age),
+        ))
+        .align_left()
+}
+
+pub fn display_option() -> impl Widget<Broker> {
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_child(label_static("keep alive(s)", UnitPoint::RIGHT))
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(KeepAliveInput)
+                        .update_data_while_editing(true)
+                        .validate_while_editing(true)
+                        .delegate(
+                            TextBoxErrorDelegate::new(ID_KEEP_ALIVE, check_keep_alive)
+                                .sends_partial_errors(true),
+                        )
+                        .fix_width(TEXTBOX_WIDTH)
+                        .lens(Broker::option.then(ConnectOption::keep_alive)),
+                )
+                .with_child(error_display_widget(ID_KEEP_ALIVE))
+                .align_left(),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(label_static("clean session", UnitPoint::RIGHT))
+                .with_child(Switch::new().lens(Broker::option.then(ConnectOption::clean_session)))
+                .align_left(),
+        )
+        .with_child(number_input(
+            "max in size",
+            ID_MAX_INCOMING_SIZE,
+            Broker::option.then(ConnectOption::max_incoming_packet_size),
+        ))
+        .with_child(number_input(
+            "max out size",
+            ID_MAX_OUTGOING_SIZE,
+            Broker::option.then(ConnectOption::max_outgoing_packet_size),
+        ))
+        .with_child(number_input(
+            "timeout(s)",
+            ID_CONN_TIMEOUT,
+            Broker::option.then(ConnectOption::conn_timeout),
+        ))
+        .align_left()
+}
+
+fn number_input<T: Data>(
+    name: &'static str,
+    id: WidgetId,
+    lens: impl Lens<Broker, T> + 'static,
+) -> impl Widget<Broker>
+where
+    MustInput: Formatter<T>,
+{
+    Flex::row()
+        .with_child(label_static(name, UnitPoint::RIGHT))
+        .with_child(
+            TextBox::new()
+                .with_formatter(MustInput)
+                .update_data_while_editing(true)
+                .validate_while_editing(true)
+                .delegate(TextBoxErrorDelegate::new(id, check_number).sends_partial_errors(true))
+                .fix_width(TEXTBOX_WIDTH)
+                .lens(lens),
+        )
+        .with_child(error_display_widget(id))
+        .align_left()
+}
+
 pub fn display_credential(_id: usize) -> impl Widget<Broker> {
     Either::new(
         move |data: &Broker, _: &Env| data.use_credentials,
@@ -195,23 +273,100 @@ pub fn display_signed_ty(id: usize, locale: Locale) -> impl Widget<Broker> {
                 .lens(Broker::signed_ty),
             )
             .with_child(TextBox::new().lens(Broker::self_signed_ca))
-            .with_child(open(id, locale.clone()))
+            .with_child(open(id, CertFileTy::SelfSignedCa, locale.clone()))
+            .align_left(),
+    )
+}
+
+pub fn display_client_cert(id: usize, locale: Locale) -> impl Widget<Broker> {
+    let cert = || Broker::client_cert;
+    Either::new(
+        move |data: &Broker, _: &Env| data.client_cert.enable,
+        Flex::column()
+            .with_child(
+                Flex::row()
+                    .with_child(label_static("client cert", UnitPoint::RIGHT))
+                    .with_child(Switch::new().lens(cert().then(ClientCert::enable)))
+                    .with_child(
+                        RadioGroup::row(vec![
+                            ("pem", ClientCertTy::Pem),
+                            ("pkcs12", ClientCertTy::Pkcs12),
+                        ])
+                        .lens(cert().then(ClientCert::ty)),
+                    )
+                    .align_left(),
+            )
+            .with_child(Either::new(
+                move |data: &Broker, _: &Env| data.client_cert.ty == ClientCertTy::Pem,
+                Flex::column()
+                    .with_child(
+                        Flex::row()
+                            .with_child(label_static("cert", UnitPoint::RIGHT))
+                            .with_child(TextBox::new().lens(cert().then(ClientCert::cert)))
+                            .with_child(open(id, CertFileTy::ClientCert, locale.clone()))
+                            .align_left(),
+                    )
+                    .with_child(
+                        Flex::row()
+                            .with_child(label_static("key", UnitPoint::RIGHT))
+                            .with_child(TextBox::new().lens(cert().then(ClientCert::key)))
+                            .with_child(open(id, CertFileTy::ClientKey, locale.clone()))
+                            .with_child(
+                                RadioGroup::row(vec![
+                                    ("pkcs8", ClientKeyTy::Pkcs8),
+                                    ("rsa", ClientKeyTy::Rsa),
+                                ])
+                                .lens(cert().then(ClientCert::key_ty)),
+                            )
+                            .align_left(),
+                    )
+                    .align_left(),
+                Flex::column()
+                    .with_child(
+                        Flex::row()
+                            .with_child(label_static("pkcs12", UnitPoint::RIGHT))
+                            .with_child(TextBox::new().lens(cert().then(ClientCert::pkcs12)))
+                            .with_child(open(id, CertFileTy::Pkcs12, locale.clone()))
+                            .align_left(),
+                    )
+                    .with_child(
+                        Flex::row()
+                            .with_child(label_static("password", UnitPoint::RIGHT))
+                            .with_child(
+                                TextBox::new()
+                                    .fix_width(TEXTBOX_WIDTH)
+                                    .lens(cert().then(ClientCert::pkcs12_password)),
+                            )
+                            .align_left(),
+                    )
+                    .align_left(),
+            ))
+            .align_left(),
+        Flex::row()
+            .with_child(label_static("client cert", UnitPoint::RIGHT))
+            .with_child(Switch::new().lens(cert().then(ClientCert::enable)))
             .align_left(),
     )
 }
 
-fn open(index: usize, locale: Locale) -> impl Widget<Broker> {
-    let certifacate = FileSpec::new("Certificate file", &["crt", "pem"]);
+fn open(index: usize, ty: CertFileTy, locale: Locale) -> impl Widget<Broker> {
+    let file_spec = match ty {
+        CertFileTy::SelfSignedCa | CertFileTy::ClientCert => {
+            FileSpec::new("Certificate file", &["crt", "pem"])
+        }
+        CertFileTy::ClientKey => FileSpec::new("Private key file", &["key", "pem"]),
+        CertFileTy::Pkcs12 => FileSpec::new("PKCS#12 file", &["p12", "pfx"]),
+    };
     // let default_save_name = String::from("MyFile.txt");
     let open_dialog_options = FileDialogOptions::new()
-        .allowed_types(vec![certifacate])
-        .default_type(certifacate)
+        .allowed_types(vec![file_spec])
+        .default_type(file_spec)
         // .name_label("Target")
         .title("Choose a certifacate")
         .button_text("Open");
 
     let open = Button::new(locale.open).on_click(move |ctx, _, _| {
-        ctx.submit_command(SELF_SIGNED_FILE.with(index));
+        ctx.submit_command(CERT_FILE.with((index, ty)));
         ctx.submit_command(druid::commands::SHOW_OPEN_PANEL.with(open_dialog_options.clone()))
     });
     open
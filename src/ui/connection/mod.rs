@@ -1,9 +1,14 @@
 use crate::data::click_ty::ClickTy;
-use crate::data::common::{Broker, Msg, PublicInput, SubscribeInput, SubscribeTopic};
+use crate::data::common::{
+    BenchmarkOption, Broker, ExportTy, InspectTy, Msg, MsgDirection, MsgFilter, PayloadTy,
+    PublicInput, PublishTemplate, QoS, ReplayOption, ReplaySpeed, ScheduleOption, SubscribeInput,
+    SubscribeTopic, TopicNode,
+};
 
 use crate::data::lens::{
-    LensQoSAString, MsgMsgLens, MsgPayloadTyLens, MsgQosLens, MsgTimeLens, MsgTopicLens,
-    SubscribeTopicPayloadLens,
+    BrokerFilteredMsgs, BrokerTemplatesLens, LensQoSAString, MsgInspectTyLens, MsgMsgLens,
+    MsgPayloadTyLens, MsgQosLens, MsgTimeLens, MsgTopicLens, SubscribeTopicPayloadLens,
+    VisibleTopicNodes,
 };
 use crate::data::AppEvent;
 use crate::ui::auto_scroll::AutoScrollController;
@@ -14,10 +19,11 @@ use crate::ui::common::{
 
 use crate::ui::icons::removed_icon;
 use crate::ui::ids::{
-    CLEAR_ERROR, ID_PUBLISH_MSG, ID_PUBLISH_QOS, ID_PUBLISH_TOPIC, ID_SUBSCRIBE_QOS,
-    ID_SUBSCRIBE_TOPIC, SCROLL_MSG_ID, SCROLL_SUBSCRIBE_ID, SHOW_ERROR,
+    CLEAR_ERROR, ERROR_TEXT_COLOR, EXPORT_FILE, ID_PUBLISH_MSG, ID_PUBLISH_QOS, ID_PUBLISH_TOPIC,
+    ID_SUBSCRIBE_QOS, ID_SUBSCRIBE_TOPIC, REPLAY_FILE, SCROLL_MSG_ID, SCROLL_SUBSCRIBE_ID,
+    SHOW_ERROR,
 };
-use crate::ui::payload_ty::{down_select_payload_ty, payload_ty_init};
+use crate::ui::payload_ty::{down_select_payload_ty, payload_ty_init, proto_msg_input};
 use crate::ui::qos::down_select_qos;
 use crate::ForError;
 
@@ -27,9 +33,13 @@ use crossbeam_channel::Sender;
 use druid::im::Vector;
 use druid::text::{EditableText, ValidationError};
 use druid::theme::{BORDER_LIGHT, TEXTBOX_BORDER_WIDTH};
-use druid::widget::{Align, Button, Container, Either, Flex, List, Scroll, Split, TextBox};
+use druid::widget::{
+    Align, Button, Container, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List,
+    RadioGroup, Scroll, SizedBox, Split, Switch, TextBox,
+};
 use druid::LensExt;
-use druid::{UnitPoint, Widget, WidgetExt};
+use druid::{FileDialogOptions, FileSpec, UnitPoint, Widget, WidgetExt};
+use druid_widget_nursery::DropdownSelect;
 use log::{error, warn};
 
 const NAME_WIDTH: f64 = 80.0;
@@ -45,9 +55,16 @@ pub fn display_connection(tx: Sender<AppEvent>, locale: Locale) -> Container<Bro
     .rounded(8.0)
     .border(BORDER_LIGHT, TEXTBOX_BORDER_WIDTH)
     .padding(0.5);
+    let topic_tree = Container::new(init_topic_tree(tx.clone()))
+        .rounded(8.0)
+        .border(BORDER_LIGHT, TEXTBOX_BORDER_WIDTH)
+        .padding(0.5);
     let subscribe = Container::new(
         Split::rows(
-            subscribe_list,
+            Split::rows(subscribe_list, topic_tree)
+                .split_point(0.5)
+                .bar_size(1.0)
+                .draggable(true),
             init_subscribe_input(tx.clone(), locale.clone()),
         )
         .split_point(0.65)
@@ -103,6 +120,11 @@ fn init_subscribe_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
                 ))
                 .with_child(payload_ty_init(SubscribeTopicPayloadLens))
                 .with_child(topic().lens(SubscribeTopic::topic))
+                .with_child(
+                    Label::dynamic(|data: &SubscribeTopic, _| data.fail_reason.to_string())
+                        .with_text_size(12.0)
+                        .with_text_color(ERROR_TEXT_COLOR),
+                )
                 .align_left()
                 .padding(2.0)
                 // .border(BORDER_LIGHT, TEXTBOX_BORDER_WIDTH)
@@ -130,7 +152,91 @@ fn init_subscribe_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
     scroll
 }
 
+/// 收到过的topic按层级显示，点击节点将topic填入订阅和发布的输入框
+fn init_topic_tree(tx: Sender<AppEvent>) -> impl Widget<Broker> {
+    let list: List<TopicNode> = List::new(move || {
+        let tx = tx.clone();
+        // 用缩进表示层级，有子节点的可以折叠
+        let toggle = Label::dynamic(|data: &TopicNode, _| {
+            let mark = if !data.has_children {
+                "  "
+            } else if data.expanded {
+                "▾ "
+            } else {
+                "▸ "
+            };
+            format!("{}{}", "    ".repeat(data.depth), mark)
+        })
+        .on_click(|_ctx, data: &mut TopicNode, _env| {
+            if data.has_children {
+                data.expanded = !data.expanded;
+            }
+        });
+        let node = Flex::row()
+            .with_child(toggle)
+            .with_flex_child(
+                Label::dynamic(|data: &TopicNode, _| {
+                    if data.name.is_empty() {
+                        "/".to_string()
+                    } else {
+                        data.name.to_string()
+                    }
+                })
+                .controller(RightClickToCopy)
+                .expand_width(),
+                1.0,
+            )
+            .with_child(Either::new(
+                |data: &TopicNode, _| data.retained,
+                Label::new("R").with_text_size(12.0),
+                SizedBox::empty(),
+            ))
+            .with_child(Either::new(
+                |data: &TopicNode, _| data.has_msg,
+                Label::dynamic(|data: &TopicNode, _| format!("{} {}", data.count, data.last_time))
+                    .with_text_size(12.0),
+                SizedBox::empty(),
+            ));
+        let payload = Either::new(
+            |data: &TopicNode, _| data.has_msg,
+            Label::dynamic(|data: &TopicNode, _| {
+                format!(
+                    "{}{}",
+                    "    ".repeat(data.depth + 1),
+                    data.last_payload.lines().next().unwrap_or_default()
+                )
+            })
+            .with_line_break_mode(LineBreaking::Clip)
+            .with_text_size(12.0),
+            SizedBox::empty(),
+        );
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(node)
+            .with_child(payload)
+            .padding((2.0, 1.0))
+            .expand_width()
+            .on_click(move |_ctx, data: &mut TopicNode, _env| {
+                if tx
+                    .send(AppEvent::TouchClick(ClickTy::TopicNode(
+                        data.broker_id,
+                        data.path.clone(),
+                    )))
+                    .is_err()
+                {
+                    error!("fail to send event")
+                }
+            })
+    });
+    Scroll::<Vector<TopicNode>, List<TopicNode>>::new(list)
+        .vertical()
+        .lens(VisibleTopicNodes)
+        .align_vertical(UnitPoint::TOP)
+        .expand_width()
+}
+
 fn init_msgs_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
+    let retry_tx = tx.clone();
     let list: List<Msg> = List::new(move || {
         Either::new(
             |data: &Msg, _env| data.is_public(),
@@ -154,6 +260,16 @@ fn init_msgs_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
                                     ))
                                     .with_child(payload_ty_init(MsgPayloadTyLens))
                                     .with_flex_child(topic().lens(MsgTopicLens), 1.0)
+                                    .with_child(
+                                        Label::dynamic(|data: &Msg, _| data.size().to_string())
+                                            .with_text_size(12.0),
+                                    )
+                                    .with_child(
+                                        Label::dynamic(|data: &Msg, _| data.latency().to_string())
+                                            .with_text_size(12.0),
+                                    )
+                                    .with_child(properties_button())
+                                    .with_child(inspector_button())
                                     .expand_width(),
                             )
                             .with_child(
@@ -166,6 +282,9 @@ fn init_msgs_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
                                     .lens(MsgMsgLens)
                                     .padding(1.5),
                             )
+                            .with_child(publish_fail_display(retry_tx.clone()))
+                            .with_child(properties_display())
+                            .with_child(inspector_display())
                             .fix_width(MSG_WIDTH),
                     )
                     .rounded(8.0)
@@ -189,6 +308,12 @@ fn init_msgs_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
                                     .with_child(QOS_GREEN().lens(MsgQosLens))
                                     .with_child(payload_ty_init(MsgPayloadTyLens))
                                     .with_flex_child(topic().lens(MsgTopicLens), 1.0)
+                                    .with_child(
+                                        Label::dynamic(|data: &Msg, _| data.size().to_string())
+                                            .with_text_size(12.0),
+                                    )
+                                    .with_child(properties_button())
+                                    .with_child(inspector_button())
                                     .expand_width(),
                             )
                             .with_child(
@@ -198,7 +323,9 @@ fn init_msgs_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
                                     .expand_width()
                                     .lens(MsgMsgLens)
                                     .padding(1.5),
-                            ),
+                            )
+                            .with_child(properties_display())
+                            .with_child(inspector_display()),
                     )
                     .rounded(8.0)
                     .border(BORDER_LIGHT, 1.0)
@@ -215,13 +342,31 @@ fn init_msgs_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
         .vertical()
         .controller(AutoScrollController)
         .with_id(SCROLL_MSG_ID)
-        .lens(Broker::msgs)
+        .lens(BrokerFilteredMsgs)
         .align_vertical(UnitPoint::TOP)
         .expand_width()
         .expand_height()
         .border(BORDER_LIGHT, TEXTBOX_BORDER_WIDTH);
     let clear_tx = tx.clone();
+    let history_tx = tx.clone();
+    let resume_tx = tx.clone();
     let tools = Flex::row()
+        .with_child(Either::new(
+            |data: &Broker, _| data.paused,
+            Button::new("Resume").on_click(move |_, data: &mut Broker, _| {
+                data.resume();
+                if resume_tx.send(AppEvent::UpdateScrollMsgWin).is_err() {
+                    error!("could not to send scroll command");
+                }
+            }),
+            Button::new("Pause").on_click(|_, data: &mut Broker, _| data.pause()),
+        ))
+        .with_child(Either::new(
+            |data: &Broker, _| data.paused,
+            Label::dynamic(|data: &Broker, _| format!("queued: {}", data.paused_msgs.len()))
+                .padding(BUTTON_PADDING),
+            SizedBox::empty(),
+        ))
         .with_child(
             Button::new("Clear").on_click(move |_, data: &mut Broker, _| {
                 if clear_tx.send(AppEvent::TouchClearMsg(data.id)).is_err() {
@@ -229,12 +374,416 @@ fn init_msgs_list(tx: Sender<AppEvent>) -> impl Widget<Broker> {
                 }
             }),
         )
+        .with_child(
+            Button::new("Earlier").on_click(move |_, data: &mut Broker, _| {
+                if history_tx
+                    .send(AppEvent::TouchLoadHistoryMsg(data.id))
+                    .is_err()
+                {
+                    error!("could not to send load history command");
+                }
+            }),
+        )
+        .with_child(export_button())
+        .with_child(Button::new("Replay").on_click(|_, data: &mut Broker, _| {
+            data.replay.show = !data.replay.show;
+        }))
+        .with_child(
+            Button::new("Benchmark").on_click(|_, data: &mut Broker, _| {
+                data.benchmark.show = !data.benchmark.show;
+            }),
+        )
+        .with_child(
+            Label::dynamic(|data: &Broker, _| format!("evicted: {}", data.evicted))
+                .padding(BUTTON_PADDING),
+        )
+        .with_child(
+            Label::dynamic(|data: &Broker, _| format!("{}", data.tab_status.err))
+                .with_text_color(ERROR_TEXT_COLOR)
+                .padding(BUTTON_PADDING),
+        )
         .align_left();
     Flex::column()
         .with_child(tools)
+        .with_child(
+            Label::dynamic(|data: &Broker, _| data.latency.to_text())
+                .with_text_size(12.0)
+                .padding(BUTTON_PADDING)
+                .align_left(),
+        )
+        .with_child(Either::new(
+            |data: &Broker, _| data.replay.show,
+            init_replay(tx.clone()),
+            SizedBox::empty(),
+        ))
+        .with_child(Either::new(
+            |data: &Broker, _| data.benchmark.show,
+            init_benchmark(tx),
+            SizedBox::empty(),
+        ))
+        .with_child(init_msg_filter())
         .with_flex_child(scroll, 1.0)
 }
 
+/// 通过保存文件对话框导出消息，按后缀选择json lines或csv
+fn export_button() -> impl Widget<Broker> {
+    let jsonl = FileSpec::new("JSON Lines", &["jsonl"]);
+    let csv = FileSpec::new("CSV", &["csv"]);
+    let save_dialog_options = FileDialogOptions::new()
+        .allowed_types(vec![jsonl, csv])
+        .default_type(jsonl)
+        .default_name("msgs.jsonl")
+        .title("Export messages")
+        .button_text("Export");
+    Button::new("Export").on_click(move |ctx, data: &mut Broker, _| {
+        ctx.submit_command(EXPORT_FILE.with((data.id, ExportTy::Msgs)));
+        ctx.submit_command(druid::commands::SHOW_SAVE_PANEL.with(save_dialog_options.clone()))
+    })
+}
+
+/// 回放json lines记录：文件、速度、topic前缀替换、循环
+fn init_replay(tx: Sender<AppEvent>) -> impl Widget<Broker> {
+    let replay = || Broker::replay;
+    let jsonl = FileSpec::new("JSON Lines", &["jsonl"]);
+    let open_dialog_options = FileDialogOptions::new()
+        .allowed_types(vec![jsonl])
+        .default_type(jsonl)
+        .title("Choose a capture")
+        .button_text("Open");
+    let stop_tx = tx.clone();
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_child(label_static("file", UnitPoint::RIGHT))
+                .with_flex_child(
+                    TextBox::new()
+                        .lens(replay().then(ReplayOption::file))
+                        .expand_width(),
+                    1.0,
+                )
+                .with_child(
+                    Button::new("Open").on_click(move |ctx, data: &mut Broker, _| {
+                        ctx.submit_command(REPLAY_FILE.with(data.id));
+                        ctx.submit_command(
+                            druid::commands::SHOW_OPEN_PANEL.with(open_dialog_options.clone()),
+                        )
+                    }),
+                ),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(
+                    RadioGroup::row(vec![
+                        ("original", ReplaySpeed::Original),
+                        ("speed", ReplaySpeed::Multiplier),
+                        ("as fast", ReplaySpeed::AsFast),
+                    ])
+                    .lens(replay().then(ReplayOption::speed)),
+                )
+                .with_child(
+                    TextBox::new()
+                        .fix_width(PULL_DOWN_WIDTH)
+                        .lens(replay().then(ReplayOption::multiplier))
+                        .disabled_if(|data: &Broker, _| {
+                            data.replay.speed != ReplaySpeed::Multiplier
+                        }),
+                )
+                .with_child(label_static("loop", UnitPoint::RIGHT))
+                .with_child(Switch::new().lens(replay().then(ReplayOption::looped))),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(label_static("prefix", UnitPoint::RIGHT))
+                .with_flex_child(
+                    TextBox::new()
+                        .with_placeholder("from")
+                        .lens(replay().then(ReplayOption::prefix_from))
+                        .expand_width(),
+                    1.0,
+                )
+                .with_flex_child(
+                    TextBox::new()
+                        .with_placeholder("to")
+                        .lens(replay().then(ReplayOption::prefix_to))
+                        .expand_width(),
+                    1.0,
+                )
+                .with_child(Either::new(
+                    |data: &Broker, _| data.replay.running,
+                    Button::new("Stop").on_click(move |_, data: &mut Broker, _| {
+                        if stop_tx.send(AppEvent::ToStopReplay(data.id)).is_err() {
+                            error!("fail to send event");
+                        }
+                    }),
+                    Button::new("Start").on_click(move |_, data: &mut Broker, _| {
+                        if !data.tab_status.connected {
+                            warn!("could not replay: not connected");
+                            return;
+                        }
+                        match data.replay_config() {
+                            Ok(config) => {
+                                data.replay.running = true;
+                                if tx.send(AppEvent::ToReplay(data.id, config)).is_err() {
+                                    error!("fail to send event");
+                                }
+                            }
+                            Err(e) => warn!("could not replay: {}", e),
+                        }
+                    }),
+                )),
+        )
+        .padding(BUTTON_PADDING)
+}
+
+/// 压测：以当前broker的配置建立多个连接，结果可导出为json
+fn init_benchmark(tx: Sender<AppEvent>) -> impl Widget<Broker> {
+    let benchmark = || Broker::benchmark;
+    let number = |name: &'static str, lens| {
+        Flex::row()
+            .with_child(label_static(name, UnitPoint::RIGHT))
+            .with_child(TextBox::new().lens(lens).fix_width(PULL_DOWN_WIDTH))
+    };
+    let json = FileSpec::new("JSON", &["json"]);
+    let save_dialog_options = FileDialogOptions::new()
+        .allowed_types(vec![json])
+        .default_type(json)
+        .default_name("benchmark.json")
+        .title("Export benchmark result")
+        .button_text("Export");
+    let stop_tx = tx.clone();
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_child(number(
+                    "clients",
+                    benchmark().then(BenchmarkOption::clients),
+                ))
+                .with_child(number("rate/s", benchmark().then(BenchmarkOption::rate)))
+                .with_child(number(
+                    "payload",
+                    benchmark().then(BenchmarkOption::payload_size),
+                ))
+                .with_child(number(
+                    "duration(s)",
+                    benchmark().then(BenchmarkOption::duration),
+                )),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(label_static("topic", UnitPoint::RIGHT))
+                .with_flex_child(
+                    TextBox::new()
+                        .lens(benchmark().then(BenchmarkOption::topic))
+                        .expand_width(),
+                    1.0,
+                )
+                .with_child(
+                    down_select_qos()
+                        .lens(benchmark().then(BenchmarkOption::qos))
+                        .fix_width(PULL_DOWN_WIDTH),
+                )
+                .with_child(Either::new(
+                    |data: &Broker, _| data.benchmark.running,
+                    Button::new("Stop").on_click(move |_, data: &mut Broker, _| {
+                        if stop_tx.send(AppEvent::ToStopBenchmark(data.id)).is_err() {
+                            error!("fail to send event");
+                        }
+                    }),
+                    Button::new("Start").on_click(move |_, data: &mut Broker, _| {
+                        // 只校验连接参数，不改变当前tab的连接状态
+                        let mut broker = data.clone();
+                        if let Err(e) = broker.init_connection() {
+                            warn!("could not start benchmark: {}", e);
+                            return;
+                        }
+                        match data.benchmark.to_config() {
+                            Ok(config) => {
+                                data.benchmark.running = true;
+                                data.benchmark.summary = Default::default();
+                                data.benchmark.report = None;
+                                if tx.send(AppEvent::ToBenchmark(broker, config)).is_err() {
+                                    error!("fail to send event");
+                                }
+                            }
+                            Err(e) => warn!("could not start benchmark: {}", e),
+                        }
+                    }),
+                ))
+                .with_child(Either::new(
+                    |data: &Broker, _| data.benchmark.report.is_some(),
+                    Button::new("Export").on_click(move |ctx, data: &mut Broker, _| {
+                        ctx.submit_command(EXPORT_FILE.with((data.id, ExportTy::Benchmark)));
+                        ctx.submit_command(
+                            druid::commands::SHOW_SAVE_PANEL.with(save_dialog_options.clone()),
+                        )
+                    }),
+                    SizedBox::empty(),
+                )),
+        )
+        .with_child(
+            Label::dynamic(|data: &Broker, _| data.benchmark.summary.to_string())
+                .with_text_size(12.0)
+                .align_left(),
+        )
+        .padding(BUTTON_PADDING)
+}
+
+fn init_msg_filter() -> impl Widget<Broker> {
+    let filter = || Broker::msg_filter;
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_child(label_static("topic", UnitPoint::RIGHT))
+                .with_flex_child(
+                    TextBox::new()
+                        .with_placeholder("a/+/#")
+                        .lens(filter().then(MsgFilter::topic))
+                        .expand_width(),
+                    1.0,
+                )
+                .with_child(label_static("payload", UnitPoint::RIGHT))
+                .with_flex_child(
+                    TextBox::new()
+                        .lens(filter().then(MsgFilter::payload))
+                        .expand_width(),
+                    1.0,
+                )
+                .with_child(label_static("regex", UnitPoint::RIGHT))
+                .with_child(Switch::new().lens(filter().then(MsgFilter::regex))),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(
+                    DropdownSelect::new(vec![
+                        ("all", MsgDirection::All),
+                        ("public", MsgDirection::Public),
+                        ("subscribe", MsgDirection::Subscribe),
+                    ])
+                    .lens(filter().then(MsgFilter::direction)),
+                )
+                .with_child(
+                    DropdownSelect::new(vec![
+                        ("qos", None),
+                        ("0", Some(QoS::AtMostOnce)),
+                        ("1", Some(QoS::AtLeastOnce)),
+                        ("2", Some(QoS::ExactlyOnce)),
+                    ])
+                    .lens(filter().then(MsgFilter::qos)),
+                )
+                .with_flex_child(
+                    TextBox::new()
+                        .with_placeholder("start: 2023-01-01 00:00:00")
+                        .lens(filter().then(MsgFilter::start))
+                        .expand_width(),
+                    1.0,
+                )
+                .with_flex_child(
+                    TextBox::new()
+                        .with_placeholder("end: 2023-01-01 23:59:59")
+                        .lens(filter().then(MsgFilter::end))
+                        .expand_width(),
+                    1.0,
+                )
+                .with_child(
+                    Button::new("Filter")
+                        .on_click(|_, data: &mut Broker, _| data.msg_filter.apply()),
+                )
+                .with_child(
+                    Button::new("Reset")
+                        .on_click(|_, data: &mut Broker, _| data.msg_filter.reset()),
+                ),
+        )
+        .with_child(
+            Label::dynamic(|data: &Broker, _| format!("{}", data.msg_filter.err))
+                .with_text_color(ERROR_TEXT_COLOR)
+                .align_left(),
+        )
+        .padding(BUTTON_PADDING)
+}
+
+fn properties_button() -> impl Widget<Msg> {
+    Either::new(
+        |data: &Msg, _env| !data.properties().is_empty(),
+        Button::new("props")
+            .on_click(|_, data: &mut Msg, _| data.toggle_properties())
+            .padding(1.0),
+        SizedBox::empty(),
+    )
+}
+
+fn properties_display() -> impl Widget<Msg> {
+    Either::new(
+        |data: &Msg, _env| data.show_properties(),
+        Label::dynamic(|data: &Msg, _| data.properties().to_display())
+            .with_text_size(12.0)
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .expand_width()
+            .padding(1.5),
+        SizedBox::empty(),
+    )
+}
+
+/// 发布失败或超时的原因，以及重发按钮
+fn publish_fail_display(tx: Sender<AppEvent>) -> impl Widget<Msg> {
+    Either::new(
+        |data: &Msg, _env| data.is_failed(),
+        Flex::row()
+            .with_flex_child(
+                Label::dynamic(|data: &Msg, _| data.fail_reason())
+                    .with_text_size(12.0)
+                    .with_text_color(ERROR_TEXT_COLOR)
+                    .with_line_break_mode(LineBreaking::WordWrap)
+                    .expand_width(),
+                1.0,
+            )
+            .with_child(
+                Button::new("Retry")
+                    .on_click(move |_, data: &mut Msg, _| {
+                        if let Msg::Public(msg) = data {
+                            if tx.send(AppEvent::TouchRetryPublish(msg.trace_id)).is_err() {
+                                error!("could not to send retry command");
+                            }
+                        }
+                    })
+                    .padding(1.0),
+            )
+            .padding(1.5),
+        SizedBox::empty(),
+    )
+}
+
+fn inspector_button() -> impl Widget<Msg> {
+    Button::new("detail")
+        .on_click(|_, data: &mut Msg, _| data.toggle_inspector())
+        .padding(1.0)
+}
+
+/// 以其他格式查看原始payload
+fn inspector_display() -> impl Widget<Msg> {
+    Either::new(
+        |data: &Msg, _env| data.show_inspector(),
+        Flex::column()
+            .with_child(
+                RadioGroup::row(vec![
+                    ("text", InspectTy::Text),
+                    ("json", InspectTy::Json),
+                    ("hex", InspectTy::Hex),
+                    ("base64", InspectTy::Base64),
+                ])
+                .lens(MsgInspectTyLens),
+            )
+            .with_child(
+                Label::dynamic(|data: &Msg, _| data.inspect())
+                    .with_font(PAYLOAD_FONT_SIZE)
+                    .with_line_break_mode(LineBreaking::WordWrap)
+                    .expand_width()
+                    .padding(1.5),
+            )
+            .cross_axis_alignment(CrossAxisAlignment::Start),
+        SizedBox::empty(),
+    )
+}
+
 //
 fn init_subscribe_input(tx: Sender<AppEvent>, locale: Locale) -> impl Widget<Broker> {
     let subscribe_tx = tx.clone();
@@ -270,6 +819,13 @@ fn init_subscribe_input(tx: Sender<AppEvent>, locale: Locale) -> impl Widget<Bro
                         .fix_width(PULL_DOWN_WIDTH)
                         .lens(Broker::subscribe_input.then(SubscribeInput::payload_ty)),
                 )
+                .with_flex_child(
+                    proto_msg_input(
+                        |data: &Broker| data.subscribe_input.payload_ty == PayloadTy::Protobuf,
+                        Broker::subscribe_input.then(SubscribeInput::proto_msg),
+                    ),
+                    1.0,
+                )
                 .align_left(),
         )
         .with_child(
@@ -298,9 +854,131 @@ fn init_subscribe_input(tx: Sender<AppEvent>, locale: Locale) -> impl Widget<Bro
     connection
 }
 
+/// 发布模板：点击名称加载到发布输入，按名称保存当前输入
+fn init_publish_templates(tx: Sender<AppEvent>) -> impl Widget<Broker> {
+    let remove_tx = tx.clone();
+    let list = List::new(move || {
+        let remove_tx = remove_tx.clone();
+        Flex::row()
+            .with_child(
+                Label::dynamic(|data: &(PublicInput, PublishTemplate), _| data.1.name.to_string())
+                    .on_click(|_ctx, data: &mut (PublicInput, PublishTemplate), _env| {
+                        let (input, template) = data;
+                        input.load_template(template);
+                    }),
+            )
+            .with_child(svg(removed_icon()).on_click(
+                move |_ctx, data: &mut (PublicInput, PublishTemplate), _env| {
+                    if remove_tx
+                        .send(AppEvent::TouchRemoveTemplate(
+                            data.0.broker_id,
+                            data.1.name.clone(),
+                        ))
+                        .is_err()
+                    {
+                        error!("fail to send event")
+                    }
+                },
+            ))
+            .border(BORDER_LIGHT, TEXTBOX_BORDER_WIDTH)
+            .rounded(3.0)
+            .padding(1.0)
+    })
+    .horizontal();
+    Flex::row()
+        .with_child(label_static("template", UnitPoint::RIGHT).fix_width(NAME_WIDTH))
+        .with_flex_child(
+            Scroll::new(list)
+                .horizontal()
+                .lens(BrokerTemplatesLens)
+                .expand_width(),
+            1.0,
+        )
+        .with_child(
+            TextBox::new()
+                .with_placeholder("name")
+                .lens(Broker::template_name)
+                .fix_width(NAME_WIDTH),
+        )
+        .with_child(
+            Button::new("Save").on_click(move |_, data: &mut Broker, _| {
+                if tx.send(AppEvent::TouchSaveTemplate(data.id)).is_err() {
+                    error!("fail to send event");
+                }
+            }),
+        )
+        .align_left()
+}
+
+/// 定时发布当前的发布输入，显示已发送和已ack的数量
+fn init_schedule(tx: Sender<AppEvent>) -> impl Widget<Broker> {
+    let schedule = || Broker::schedule;
+    let stop_tx = tx.clone();
+    Flex::row()
+        .with_child(label_static("interval", UnitPoint::RIGHT).fix_width(NAME_WIDTH))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("ms")
+                .lens(schedule().then(ScheduleOption::interval))
+                .fix_width(PULL_DOWN_WIDTH),
+        )
+        .with_child(label_static("count", UnitPoint::RIGHT))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("∞")
+                .lens(schedule().then(ScheduleOption::count))
+                .fix_width(PULL_DOWN_WIDTH),
+        )
+        .with_child(Either::new(
+            |data: &Broker, _| data.schedule.running,
+            Button::new("Stop").on_click(move |_, data: &mut Broker, _| {
+                if stop_tx.send(AppEvent::ToStopSchedule(data.id)).is_err() {
+                    error!("fail to send event");
+                }
+            }),
+            Button::new("Start").on_click(move |_, data: &mut Broker, _| {
+                if !data.tab_status.connected {
+                    warn!("could not start scheduled publish: not connected");
+                    return;
+                }
+                if data.public_input.topic.is_empty() {
+                    warn!("topic is empty");
+                    return;
+                }
+                match data.schedule.to_config(&data.public_input) {
+                    Ok(config) => {
+                        data.schedule.start();
+                        if tx.send(AppEvent::ToStartSchedule(data.id, config)).is_err() {
+                            error!("fail to send event");
+                        }
+                    }
+                    Err(e) => warn!("could not start scheduled publish: {}", e),
+                }
+            }),
+        ))
+        .with_child(
+            Label::dynamic(|data: &Broker, _| {
+                format!(
+                    "{}sent: {} acked: {}",
+                    if data.schedule.running {
+                        "running "
+                    } else {
+                        ""
+                    },
+                    data.schedule.sent,
+                    data.schedule.acked
+                )
+            })
+            .padding(BUTTON_PADDING),
+        )
+        .align_left()
+}
+
 fn init_public_input(tx: Sender<AppEvent>, locale: Locale) -> impl Widget<Broker> {
     let public_tx = tx.clone();
     let connection = Flex::column()
+        .with_child(init_publish_templates(tx.clone()))
+        .with_child(init_schedule(tx.clone()))
         .with_child(
             Flex::row()
                 .with_child(label_static("topic", UnitPoint::RIGHT).fix_width(NAME_WIDTH))
@@ -332,6 +1010,13 @@ fn init_public_input(tx: Sender<AppEvent>, locale: Locale) -> impl Widget<Broker
                         .lens(Broker::public_input.then(PublicInput::payload_ty))
                         .fix_width(PULL_DOWN_WIDTH),
                 )
+                .with_flex_child(
+                    proto_msg_input(
+                        |data: &Broker| data.public_input.payload_ty == PayloadTy::Protobuf,
+                        Broker::public_input.then(PublicInput::proto_msg),
+                    ),
+                    1.0,
+                )
                 // .with_child(error_display_widget(ID_PUBLISH_QOS))
                 .align_left(),
         )
@@ -1,5 +1,6 @@
 use crate::data::common::QoS;
 use crate::data::AString;
+use crate::util::consts::MIN_KEEP_ALIVE;
 
 use crate::ForError;
 use druid::text::Formatter;
@@ -65,6 +66,68 @@ impl Formatter<QoS> for MustInput {
         parse_to_qos(input).to_validation_error()
     }
 }
+impl Formatter<usize> for MustInput {
+    fn format(&self, value: &usize) -> String {
+        value.to_string()
+    }
+
+    fn validate_partial_input(&self, input: &str, _sel: &Selection) -> Validation {
+        parse_to_number::<usize>(input).to_validation()
+    }
+    fn value(&self, input: &str) -> Result<usize, ValidationError> {
+        parse_to_number(input).to_validation_error()
+    }
+}
+impl Formatter<u16> for MustInput {
+    fn format(&self, value: &u16) -> String {
+        value.to_string()
+    }
+
+    fn validate_partial_input(&self, input: &str, _sel: &Selection) -> Validation {
+        parse_to_number::<u16>(input).to_validation()
+    }
+    fn value(&self, input: &str) -> Result<u16, ValidationError> {
+        parse_to_number(input).to_validation_error()
+    }
+}
+impl Formatter<u32> for MustInput {
+    fn format(&self, value: &u32) -> String {
+        value.to_string()
+    }
+
+    fn validate_partial_input(&self, input: &str, _sel: &Selection) -> Validation {
+        parse_to_number::<u32>(input).to_validation()
+    }
+    fn value(&self, input: &str) -> Result<u32, ValidationError> {
+        parse_to_number(input).to_validation_error()
+    }
+}
+impl Formatter<u64> for MustInput {
+    fn format(&self, value: &u64) -> String {
+        value.to_string()
+    }
+
+    fn validate_partial_input(&self, input: &str, _sel: &Selection) -> Validation {
+        parse_to_number::<u64>(input).to_validation()
+    }
+    fn value(&self, input: &str) -> Result<u64, ValidationError> {
+        parse_to_number(input).to_validation_error()
+    }
+}
+/// keep alive至少为5s
+pub struct KeepAliveInput;
+impl Formatter<u16> for KeepAliveInput {
+    fn format(&self, value: &u16) -> String {
+        value.to_string()
+    }
+
+    fn validate_partial_input(&self, input: &str, _sel: &Selection) -> Validation {
+        parse_to_number::<u16>(input).to_validation()
+    }
+    fn value(&self, input: &str) -> Result<u16, ValidationError> {
+        parse_to_keep_alive(input).to_validation_error()
+    }
+}
 pub trait Portable<T> {
     fn to_validation(self) -> Validation;
     fn to_validation_error(self) -> Result<T, ValidationError>;
@@ -89,6 +152,20 @@ pub fn parse_to_port(input: &str) -> Result<Option<u16>, ForError> {
     }
     Ok(Some(input.parse().map_err(|_| ForError::InvalidPort)?))
 }
+/// 空输入视为0
+pub fn parse_to_number<T: std::str::FromStr + Default>(input: &str) -> Result<T, ForError> {
+    if input.is_empty() {
+        return Ok(T::default());
+    }
+    input.parse().map_err(|_| ForError::InvalidNumber)
+}
+pub fn parse_to_keep_alive(input: &str) -> Result<u16, ForError> {
+    let keep_alive = input.parse().map_err(|_| ForError::InvalidNumber)?;
+    if keep_alive < MIN_KEEP_ALIVE {
+        return Err(ForError::InvalidKeepAlive);
+    }
+    Ok(keep_alive)
+}
 pub fn parse_to_no_empty(input: &str) -> Result<AString, ForError> {
     // debug!("{}", input);
     if input.is_empty() {
@@ -131,3 +208,15 @@ pub fn check_port(input: &str) -> bool {
     }
     true
 }
+pub fn check_number(input: &str) -> bool {
+    if parse_to_number::<u64>(input).is_err() {
+        return false;
+    }
+    true
+}
+pub fn check_keep_alive(input: &str) -> bool {
+    if parse_to_keep_alive(input).is_err() {
+        return false;
+    }
+    true
+}
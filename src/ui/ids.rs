@@ -1,3 +1,4 @@
+use crate::data::common::{CertFileTy, ExportTy};
 use druid::text::ValidationError;
 use druid::widget::{TextBoxEvent, ValidationDelegate};
 use druid::{
@@ -24,6 +25,15 @@ pub const SCROLL_SUBSCRIBE_ID: WidgetId = WidgetId::reserved(14);
 /// 发布、接收订阅发布的窗口
 pub const SCROLL_MSG_ID: WidgetId = WidgetId::reserved(15);
 
+pub const ID_RETENTION_COUNT: WidgetId = WidgetId::reserved(16);
+pub const ID_RETENTION_BYTES: WidgetId = WidgetId::reserved(17);
+pub const ID_RETENTION_AGE: WidgetId = WidgetId::reserved(18);
+
+pub const ID_KEEP_ALIVE: WidgetId = WidgetId::reserved(19);
+pub const ID_MAX_INCOMING_SIZE: WidgetId = WidgetId::reserved(20);
+pub const ID_MAX_OUTGOING_SIZE: WidgetId = WidgetId::reserved(21);
+pub const ID_CONN_TIMEOUT: WidgetId = WidgetId::reserved(22);
+
 pub const ERROR_TEXT_COLOR: Color = Color::rgb8(0xB6, 0x00, 0x04);
 
 /// Sent by the [`TextBoxErrorDelegate`] when an error should be displayed.
@@ -147,6 +157,12 @@ pub const SELECTOR_TABS_CLOSE: Selector<usize> = Selector::new("tabs.close");
 
 pub const SELECTOR_AUTO_SCROLL: Selector<()> = Selector::new("scroll.auto");
 
-pub const SELF_SIGNED_FILE: Selector<usize> = Selector::new("self-signed-file");
+pub const CERT_FILE: Selector<(usize, CertFileTy)> = Selector::new("cert-file");
+
+/// 导出消息或压测结果，参数为broker id
+pub const EXPORT_FILE: Selector<(usize, ExportTy)> = Selector::new("export-file");
+
+/// 选择回放文件，参数为broker id
+pub const REPLAY_FILE: Selector<usize> = Selector::new("replay-file");
 
 pub const TIPS: Selector<()> = Selector::new("tips");
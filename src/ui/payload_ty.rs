@@ -1,5 +1,6 @@
 use crate::data::common::PayloadTy;
-use druid::widget::Label;
+use crate::data::AString;
+use druid::widget::{Either, Label, SizedBox, TextBox};
 use druid::{Data, Env, Lens, Widget, WidgetExt};
 use druid_widget_nursery::DropdownSelect;
 use std::sync::Arc;
@@ -9,9 +10,30 @@ pub fn down_select_payload_ty() -> impl Widget<PayloadTy> {
         ("Text", PayloadTy::Text),
         ("Json", PayloadTy::Json),
         ("Hex", PayloadTy::Hex),
+        ("Protobuf", PayloadTy::Protobuf),
+        ("Cbor", PayloadTy::Cbor),
+        ("MsgPack", PayloadTy::MsgPack),
+        ("Base64", PayloadTy::Base64),
+        ("Gzip", PayloadTy::Gzip),
+        ("Zstd", PayloadTy::Zstd),
     ])
 }
 
+/// 选择Protobuf时，输入message类型，如 package.Message
+pub fn proto_msg_input<T: Data>(
+    is_protobuf: impl Fn(&T) -> bool + 'static,
+    proto_msg: impl Lens<T, AString> + 'static,
+) -> impl Widget<T> {
+    Either::new(
+        move |data: &T, _: &Env| is_protobuf(data),
+        TextBox::new()
+            .with_placeholder("package.Message")
+            .lens(proto_msg)
+            .expand_width(),
+        SizedBox::empty(),
+    )
+}
+
 pub fn payload_ty_init<T: Data>(data: impl Lens<T, Arc<String>> + 'static) -> impl Widget<T> {
     // TextBox::<Arc<String>>::new()
     //     .fix_width(15.0)
@@ -0,0 +1,65 @@
+//! pkcs12客户端证书的转换。for-mqtt-client只接受pem文件，连接前把pkcs12拆成
+//! 证书链和pkcs8私钥，放在 ~/.for-mqtt/cert 下
+use anyhow::{anyhow, bail, Result};
+use lazy_static::lazy_static;
+use p12_keystore::KeyStore;
+use pem::Pem;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref DIR: RwLock<PathBuf> = RwLock::new(PathBuf::new());
+}
+
+pub fn init(dir: PathBuf) {
+    if let Ok(mut global) = DIR.write() {
+        *global = dir;
+    }
+}
+
+/// 返回转换后的(证书链, 私钥)文件路径，每个broker一份，重连时覆盖
+pub fn pkcs12_to_pem(broker_id: usize, path: &str, password: &str) -> Result<(PathBuf, PathBuf)> {
+    let store = KeyStore::from_pkcs12(std::fs::read(path)?.as_slice(), password)
+        .map_err(|e| anyhow!("fail to read pkcs12 file: {}", e))?;
+    // 证书链的第一个即与私钥配对的终端证书
+    let (_, chain) = store
+        .private_key_chain()
+        .ok_or_else(|| anyhow!("no private key in pkcs12 file"))?;
+    if chain.chain().is_empty() {
+        bail!("no certificate in pkcs12 file");
+    }
+    let certs: Vec<Pem> = chain
+        .chain()
+        .iter()
+        .map(|x| Pem::new("CERTIFICATE", x.as_der()))
+        .collect();
+
+    let dir = DIR
+        .read()
+        .map_err(|_| anyhow!("cert dir is poisoned"))?
+        .clone();
+    std::fs::create_dir_all(&dir)?;
+    let cert_path = dir.join(format!("{}.crt", broker_id));
+    let key_path = dir.join(format!("{}.key", broker_id));
+    write(&cert_path, pem::encode_many(&certs).as_bytes())?;
+    write(
+        &key_path,
+        pem::encode(&Pem::new("PRIVATE KEY", chain.key())).as_bytes(),
+    )?;
+    Ok((cert_path, key_path))
+}
+
+/// 私钥文件仅当前用户可读
+fn write(path: &Path, content: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content)?;
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! cbor、msgpack解码后转为json显示
+//! 二者的数据模型比json更宽：字节串转为base64，非字符串的map key转为字符串
+use crate::util::compress::base64_encode;
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Number, Value};
+
+pub fn decode_cbor(data: &[u8]) -> Result<Value> {
+    let value = ciborium::de::from_reader::<ciborium::Value, _>(data)
+        .map_err(|e| anyhow!("invalid cbor: {}", e))?;
+    Ok(cbor_to_json(value))
+}
+
+pub fn decode_msgpack(data: &[u8]) -> Result<Value> {
+    let mut reader = data;
+    let value =
+        rmpv::decode::read_value(&mut reader).map_err(|e| anyhow!("invalid msgpack: {}", e))?;
+    Ok(msgpack_to_json(value))
+}
+
+fn cbor_to_json(value: ciborium::Value) -> Value {
+    use ciborium::Value as Cbor;
+    match value {
+        Cbor::Integer(val) => int_to_json(i128::from(val)),
+        Cbor::Bytes(val) => Value::String(base64_encode(&val)),
+        Cbor::Float(val) => float_to_json(val),
+        Cbor::Text(val) => Value::String(val),
+        Cbor::Bool(val) => Value::Bool(val),
+        Cbor::Null => Value::Null,
+        // tag仅是语义标注，显示其内容即可
+        Cbor::Tag(_, val) => cbor_to_json(*val),
+        Cbor::Array(vals) => Value::Array(vals.into_iter().map(cbor_to_json).collect()),
+        Cbor::Map(vals) => Value::Object(
+            vals.into_iter()
+                .map(|(key, val)| (to_key(cbor_to_json(key)), cbor_to_json(val)))
+                .collect::<Map<String, Value>>(),
+        ),
+        _ => Value::Null,
+    }
+}
+
+fn msgpack_to_json(value: rmpv::Value) -> Value {
+    use rmpv::Value as MsgPack;
+    match value {
+        MsgPack::Nil => Value::Null,
+        MsgPack::Boolean(val) => Value::Bool(val),
+        MsgPack::Integer(val) => match (val.as_i64(), val.as_u64()) {
+            (Some(val), _) => Value::from(val),
+            (_, Some(val)) => Value::from(val),
+            _ => Value::Null,
+        },
+        MsgPack::F32(val) => float_to_json(val as f64),
+        MsgPack::F64(val) => float_to_json(val),
+        MsgPack::String(val) => match val.into_str() {
+            Some(val) => Value::String(val),
+            None => Value::Null,
+        },
+        MsgPack::Binary(val) => Value::String(base64_encode(&val)),
+        MsgPack::Array(vals) => Value::Array(vals.into_iter().map(msgpack_to_json).collect()),
+        MsgPack::Map(vals) => Value::Object(
+            vals.into_iter()
+                .map(|(key, val)| (to_key(msgpack_to_json(key)), msgpack_to_json(val)))
+                .collect::<Map<String, Value>>(),
+        ),
+        MsgPack::Ext(ty, data) => {
+            let mut ext = Map::new();
+            ext.insert("type".to_string(), Value::from(ty));
+            ext.insert("data".to_string(), Value::String(base64_encode(&data)));
+            Value::Object(ext)
+        }
+    }
+}
+
+fn int_to_json(val: i128) -> Value {
+    if let Ok(val) = i64::try_from(val) {
+        Value::from(val)
+    } else if let Ok(val) = u64::try_from(val) {
+        Value::from(val)
+    } else {
+        // 超出json数字范围的大整数
+        Value::String(val.to_string())
+    }
+}
+
+/// NaN、inf无法表示为json数字，按字符串显示
+fn float_to_json(val: f64) -> Value {
+    match Number::from_f64(val) {
+        Some(val) => Value::Number(val),
+        None => Value::String(val.to_string()),
+    }
+}
+
+fn to_key(key: Value) -> String {
+    match key {
+        Value::String(key) => key,
+        key => key.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::util::codec::{decode_cbor, decode_msgpack};
+    use serde_json::json;
+
+    #[test]
+    fn test_decode() {
+        let cbor = ciborium::Value::Map(vec![
+            (1.into(), ciborium::Value::Bytes(vec![1, 2, 3])),
+            ("temp".into(), 21.5.into()),
+        ]);
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&cbor, &mut data).unwrap();
+        assert_eq!(
+            decode_cbor(&data).unwrap(),
+            json!({"1": "AQID", "temp": 21.5})
+        );
+
+        let msgpack = rmpv::Value::Map(vec![
+            (true.into(), rmpv::Value::Binary(vec![1, 2, 3])),
+            ("temp".into(), 21.5.into()),
+        ]);
+        let mut data = Vec::new();
+        rmpv::encode::write_value(&mut data, &msgpack).unwrap();
+        assert_eq!(
+            decode_msgpack(&data).unwrap(),
+            json!({"true": "AQID", "temp": 21.5})
+        );
+        assert!(decode_msgpack(&[0xc1]).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! base64、gzip、zstd的编解码
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// 解压后的最大长度，避免异常数据占满内存
+const MAX_DECOMPRESSED: u64 = 16 * 1024 * 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+pub fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+pub fn gunzip(data: &[u8]) -> Result<Vec<u8>> {
+    read_limited(GzDecoder::new(data)).map_err(|e| anyhow!("invalid gzip: {}", e))
+}
+
+pub fn zstd_compress(data: &[u8]) -> Result<Vec<u8>> {
+    Ok(zstd::encode_all(data, 0)?)
+}
+
+pub fn zstd_decompress(data: &[u8]) -> Result<Vec<u8>> {
+    read_limited(zstd::Decoder::new(data)?).map_err(|e| anyhow!("invalid zstd: {}", e))
+}
+
+pub fn base64_encode(data: &[u8]) -> String {
+    STANDARD.encode(data)
+}
+
+/// base64解码，若解码后为gzip、zstd数据则继续解压
+pub fn base64_decode(data: &[u8]) -> Result<Vec<u8>> {
+    let data: Vec<u8> = data
+        .iter()
+        .filter(|x| !x.is_ascii_whitespace())
+        .cloned()
+        .collect();
+    let data = STANDARD
+        .decode(data)
+        .map_err(|e| anyhow!("invalid base64: {}", e))?;
+    if data.starts_with(&GZIP_MAGIC) {
+        gunzip(data.as_slice())
+    } else if data.starts_with(&ZSTD_MAGIC) {
+        zstd_decompress(data.as_slice())
+    } else {
+        Ok(data)
+    }
+}
+
+fn read_limited(reader: impl Read) -> std::io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.take(MAX_DECOMPRESSED + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_DECOMPRESSED {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "decompressed data is too large",
+        ));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::util::compress::{
+        base64_decode, base64_encode, gunzip, gzip, zstd_compress, zstd_decompress,
+    };
+
+    #[test]
+    fn test_compress() {
+        let data = br#"{"temperature": 21.5, "humidity": 40}"#;
+        assert_eq!(gunzip(&gzip(data).unwrap()).unwrap(), data);
+        assert_eq!(
+            zstd_decompress(&zstd_compress(data).unwrap()).unwrap(),
+            data
+        );
+        assert_eq!(base64_decode(base64_encode(data).as_bytes()).unwrap(), data);
+        let wrapped = base64_encode(&gzip(data).unwrap());
+        assert_eq!(base64_decode(wrapped.as_bytes()).unwrap(), data);
+        assert!(gunzip(data).is_err());
+    }
+}
@@ -20,6 +20,24 @@ lazy_static! {
 lazy_static! {
     pub static ref TY_HEX: Arc<String> = Arc::new("H".to_string());
 }
+lazy_static! {
+    pub static ref TY_PROTOBUF: Arc<String> = Arc::new("P".to_string());
+}
+lazy_static! {
+    pub static ref TY_CBOR: Arc<String> = Arc::new("C".to_string());
+}
+lazy_static! {
+    pub static ref TY_MSGPACK: Arc<String> = Arc::new("M".to_string());
+}
+lazy_static! {
+    pub static ref TY_BASE64: Arc<String> = Arc::new("B".to_string());
+}
+lazy_static! {
+    pub static ref TY_GZIP: Arc<String> = Arc::new("G".to_string());
+}
+lazy_static! {
+    pub static ref TY_ZSTD: Arc<String> = Arc::new("Z".to_string());
+}
 
 pub trait QosToString {
     fn qos_to_string(&self) -> Arc<String>;
@@ -60,3 +78,10 @@ impl QosToString for QoSWithPacketId {
 // 6. 当前版本"#;
 
 pub const GITHUB_ADDR: &str = "https://github.com/jm-observer/for-mqtt";
+
+/// MqttOptions::set_keep_alive要求至少为5s
+pub const MIN_KEEP_ALIVE: u16 = 5;
+
+/// 回放倍速的范围，过小时间隔会溢出Duration
+pub const MIN_REPLAY_SPEED: f64 = 0.001;
+pub const MAX_REPLAY_SPEED: f64 = 1000.0;
@@ -5,7 +5,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::data::common::{Broker, Protocol, PublicInput, SignedTy, SubscribeInput, TabStatus};
-use crate::data::db::{BrokerDB, DbKey};
+use crate::data::db::{BrokerDB, DbKey, MsgDB, MsgKey};
 use crate::data::hierarchy::AppData;
 use crate::data::AppEvent;
 use druid::im::Vector;
@@ -65,7 +65,9 @@ impl ArcDb {
             broker_tabs: Default::default(),
             db: self.clone(),
             hint: "".to_string().into(),
-            self_signed_file: None,
+            cert_file: None,
+            export_broker: None,
+            replay_broker: None,
             display_history: false,
             display_broker_info: false,
             tx: self.tx.clone(),
@@ -82,7 +84,7 @@ impl ArcDb {
             name: Arc::new("".to_string()),
             addr: Arc::new("broker-cn.emqx.io".to_string()),
             port: Some(1883),
-            params: Arc::new(OPTION.to_string()),
+            option: Default::default(),
             use_credentials: false,
             user_name: Arc::new("".to_string()),
             password: Arc::new("".to_string()),
@@ -92,6 +94,7 @@ impl ArcDb {
             tls: false,
             signed_ty: SignedTy::Ca,
             self_signed_ca: Arc::new("".to_string()),
+            client_cert: Default::default(),
             subscribe_hises: Default::default(),
             subscribe_topics: Default::default(),
             msgs: Default::default(),
@@ -102,8 +105,26 @@ impl ArcDb {
                 id,
                 try_connect: false,
                 connected: false,
+                err: Default::default(),
             },
             auto_connect: true,
+            retention: Default::default(),
+            evicted: 0,
+            history_len: 0,
+            msg_filter: Default::default(),
+            filtered_cache: Default::default(),
+            paused: false,
+            paused_msgs: Default::default(),
+            msgs_bytes: 0,
+            topic_tree: Default::default(),
+            replay: Default::default(),
+            schedule: Default::default(),
+            latency: Default::default(),
+            benchmark: Default::default(),
+            templates: Default::default(),
+            template_name: Default::default(),
+            publish_counter: 0,
+            conn_generation: 0,
         }
     }
 
@@ -132,11 +153,54 @@ impl ArcDb {
             self.ids.remove(index);
             self.update_ids()?;
             self.db.remove(DbKey::broker_key(id).as_bytes()?)?;
+            self.db.drop_tree(DbKey::msgs_key(id).as_bytes()?)?;
         } else {
             warn!("not selected broker to delete");
         }
         Ok(())
     }
+    /// 消息以(时间戳, 序号)为key，保证按时间排序
+    pub fn save_msg(&self, id: usize, msg: &MsgDB) -> Result<MsgKey> {
+        let tree = self.db.open_tree(DbKey::msgs_key(id).as_bytes()?)?;
+        let key = MsgKey {
+            timestamp: msg.timestamp,
+            id: self.db.generate_id()?,
+        };
+        tree.insert(key.to_bytes(), serde_json::to_vec(msg)?)?;
+        Ok(key)
+    }
+    /// 读取key早于before的消息，最多limit条，按时间正序返回
+    pub fn read_msgs(
+        &self,
+        id: usize,
+        before: Option<MsgKey>,
+        limit: usize,
+    ) -> Result<Vec<(MsgKey, MsgDB)>> {
+        let tree = self.db.open_tree(DbKey::msgs_key(id).as_bytes()?)?;
+        let iter = match before {
+            Some(before) => tree.range(..before.to_bytes()),
+            None => tree.iter(),
+        };
+        let mut msgs = Vec::with_capacity(limit);
+        for item in iter.rev().take(limit) {
+            let (key, val) = item?;
+            let Some(key) = MsgKey::from_bytes(&key) else {
+                warn!("invalid msg key: {:?}", key);
+                continue;
+            };
+            msgs.push((key, serde_json::from_slice::<MsgDB>(&val)?));
+        }
+        msgs.reverse();
+        Ok(msgs)
+    }
+    /// 按时间正序逐条读取所有消息，用于导出
+    pub fn iter_msgs(&self, id: usize) -> Result<impl Iterator<Item = Result<MsgDB>>> {
+        let tree = self.db.open_tree(DbKey::msgs_key(id).as_bytes()?)?;
+        Ok(tree
+            .iter()
+            .values()
+            .map(|val| -> Result<MsgDB> { Ok(serde_json::from_slice(&val?)?) }))
+    }
     #[inline]
     fn update_ids(&self) -> Result<()> {
         self.db.insert(BROKERS, serde_json::to_vec(&self.ids)?)?;
@@ -150,16 +214,6 @@ impl ArcDb {
     // }
 }
 
-const OPTION: &str = r#"{
-	"keep_alive": 60,
-	"clean_session": true,
-	"max_incoming_packet_size": 10240,
-	"max_outgoing_packet_size": 10240,
-	"inflight": 100,
-	"conn_timeout": 5
-}
-        "#;
-
 #[cfg(test)]
 mod test {
 
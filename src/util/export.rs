@@ -0,0 +1,122 @@
+use crate::data::common::PayloadTy;
+use crate::data::db::{MsgDB, MsgTy};
+use crate::util::compress::base64_encode;
+use crate::util::consts::QosToString;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExportFormat {
+    JsonLines,
+    Csv,
+}
+
+impl ExportFormat {
+    /// 按文件后缀判断导出格式，默认为json lines
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Self::Csv,
+            _ => Self::JsonLines,
+        }
+    }
+}
+
+/// 导出的一行消息。payload非utf8时以base64编码，回放时读取同样的格式
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportRow {
+    pub topic: String,
+    pub direction: String,
+    pub qos: String,
+    pub retain: bool,
+    /// 毫秒
+    pub timestamp: i64,
+    pub payload_ty: String,
+    /// protobuf的message类型，回放时用于显示
+    #[serde(default)]
+    pub proto_msg: String,
+    pub payload_encoding: String,
+    pub payload: String,
+}
+
+impl From<&MsgDB> for ExportRow {
+    fn from(msg: &MsgDB) -> Self {
+        let (payload_encoding, payload) = match std::str::from_utf8(msg.payload.as_slice()) {
+            Ok(payload) => ("utf8", payload.to_string()),
+            Err(_) => ("base64", base64_encode(msg.payload.as_slice())),
+        };
+        Self {
+            topic: msg.topic.to_string(),
+            direction: match msg.ty {
+                MsgTy::Public => "public",
+                MsgTy::Subscribe => "subscribe",
+            }
+            .to_string(),
+            qos: msg.qos.qos_to_string().to_string(),
+            retain: msg.retain,
+            timestamp: msg.timestamp,
+            payload_ty: payload_ty_name(&msg.payload_ty).to_string(),
+            proto_msg: msg.proto_msg.to_string(),
+            payload_encoding: payload_encoding.to_string(),
+            payload,
+        }
+    }
+}
+
+fn payload_ty_name(ty: &PayloadTy) -> &'static str {
+    match ty {
+        PayloadTy::Text => "Text",
+        PayloadTy::Json => "Json",
+        PayloadTy::Hex => "Hex",
+        PayloadTy::Protobuf => "Protobuf",
+        PayloadTy::Cbor => "Cbor",
+        PayloadTy::MsgPack => "MsgPack",
+        PayloadTy::Base64 => "Base64",
+        PayloadTy::Gzip => "Gzip",
+        PayloadTy::Zstd => "Zstd",
+    }
+}
+
+/// 将存储的消息逐条写入文件，返回导出的条数
+pub fn export_msgs(path: &Path, msgs: impl Iterator<Item = Result<MsgDB>>) -> Result<usize> {
+    let file = File::create(path)?;
+    match ExportFormat::from_path(path) {
+        ExportFormat::JsonLines => write_json_lines(file, msgs),
+        ExportFormat::Csv => write_csv(file, msgs),
+    }
+}
+
+pub fn export_json<T: Serialize>(path: &Path, val: &T) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, val)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_json_lines<W: Write>(
+    writer: W,
+    msgs: impl Iterator<Item = Result<MsgDB>>,
+) -> Result<usize> {
+    let mut writer = BufWriter::new(writer);
+    let mut count = 0;
+    for msg in msgs {
+        serde_json::to_writer(&mut writer, &ExportRow::from(&msg?))?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+fn write_csv<W: Write>(writer: W, msgs: impl Iterator<Item = Result<MsgDB>>) -> Result<usize> {
+    let mut writer = csv::Writer::from_writer(writer);
+    let mut count = 0;
+    for msg in msgs {
+        writer.serialize(ExportRow::from(&msg?))?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
@@ -8,5 +8,7 @@ pub const CONNECT_SUCCESS: &str = "connect success!";
 pub const DISCONNECT_SUCCESS: &str = "disconnect success!";
 pub const SUBSCRIBE_SUCCESS: &str = "subscribe success!";
 pub const PUBLISH_SUCCESS: &str = "publish success!";
+pub const SAVE_TEMPLATE_SUCCESS: &str = "save template success!";
+pub const DELETE_TEMPLATE_SUCCESS: &str = "delete template success!";
 
 pub const UNSUBSCRIBE_SUCCESS: &str = "unsubscribe success!";
@@ -0,0 +1,60 @@
+//! 发布确认延迟的格式化与分段统计
+use std::time::Duration;
+
+/// 各分段的上限(微秒)，最后一段为超过1s
+const BUCKETS: [(u64, &str); 7] = [
+    (1_000, "≤1ms"),
+    (5_000, "≤5ms"),
+    (10_000, "≤10ms"),
+    (50_000, "≤50ms"),
+    (100_000, "≤100ms"),
+    (500_000, "≤500ms"),
+    (1_000_000, "≤1s"),
+];
+const OVERFLOW: &str = ">1s";
+
+pub fn format_latency(latency: Duration) -> String {
+    let micros = latency.as_micros();
+    if micros < 1_000 {
+        format!("{}µs", micros)
+    } else if micros < 1_000_000 {
+        format!("{:.1}ms", micros as f64 / 1000.0)
+    } else {
+        format!("{:.2}s", micros as f64 / 1_000_000.0)
+    }
+}
+
+/// 统计各分段的数量，samples为微秒
+pub fn bucket_counts<'a>(samples: impl Iterator<Item = &'a u64>) -> Vec<(&'static str, usize)> {
+    let mut counts: Vec<(&'static str, usize)> = BUCKETS
+        .iter()
+        .map(|(_, name)| (*name, 0))
+        .chain(std::iter::once((OVERFLOW, 0)))
+        .collect();
+    for sample in samples {
+        let index = BUCKETS
+            .iter()
+            .position(|(max, _)| sample <= max)
+            .unwrap_or(BUCKETS.len());
+        counts[index].1 += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_latency() {
+        assert_eq!(format_latency(Duration::from_micros(800)), "800µs");
+        assert_eq!(format_latency(Duration::from_micros(12_345)), "12.3ms");
+        assert_eq!(format_latency(Duration::from_millis(1500)), "1.50s");
+
+        let counts = bucket_counts([500, 1_000, 1_001, 2_000_000].iter());
+        assert_eq!(counts.len(), 8);
+        assert_eq!(counts[0], ("≤1ms", 2));
+        assert_eq!(counts[1], ("≤5ms", 1));
+        assert_eq!(counts[7], (">1s", 1));
+    }
+}
@@ -1,11 +1,20 @@
-use chrono::Local;
+use anyhow::bail;
+use chrono::{Local, NaiveDateTime, TimeZone};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 
+pub mod cert;
+pub mod codec;
+pub mod compress;
 pub mod consts;
 pub mod custom_logger;
 pub mod db;
+pub mod export;
 pub mod hint;
+pub mod latency;
+pub mod proto;
+pub mod replay;
+pub mod template;
 
 pub const ID_CHARS: [char; 62] = [
     'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
@@ -28,6 +37,19 @@ pub fn now_time() -> String {
     format!("{}", now.format("%H:%M:%S"))
 }
 
+/// 当前时间戳(毫秒)
+pub fn now_timestamp() -> i64 {
+    Local::now().timestamp_millis()
+}
+
+/// 历史消息的时间显示，需带上日期
+pub fn timestamp_to_time(timestamp: i64) -> String {
+    match Local.timestamp_millis_opt(timestamp).single() {
+        Some(time) => format!("{}", time.format("%m-%d %H:%M:%S")),
+        None => "".to_string(),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::util::general_id;
@@ -40,3 +62,12 @@ mod test {
         println!("{}", general_id());
     }
 }
+
+/// 将本地时间(%Y-%m-%d %H:%M:%S)转为时间戳(毫秒)
+pub fn time_to_timestamp(time: &str) -> anyhow::Result<i64> {
+    let time = NaiveDateTime::parse_from_str(time.trim(), "%Y-%m-%d %H:%M:%S")?;
+    match Local.from_local_datetime(&time).single() {
+        Some(time) => Ok(time.timestamp_millis()),
+        None => bail!("invalid time: {}", time),
+    }
+}
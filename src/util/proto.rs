@@ -0,0 +1,71 @@
+//! protobuf的编解码。描述文件放在 ~/.for-mqtt/proto 下，支持.proto源文件，
+//! 或编译后的descriptor set(.desc、.pb、.bin)
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use log::{error, info};
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref POOL: RwLock<DescriptorPool> = RwLock::new(DescriptorPool::new());
+}
+
+pub fn init(dir: PathBuf) {
+    match load_pool(&dir) {
+        Ok(pool) => {
+            info!("load {} protobuf messages", pool.all_messages().count());
+            if let Ok(mut global) = POOL.write() {
+                *global = pool;
+            }
+        }
+        Err(e) => error!("fail to load protobuf descriptors: {:?}", e),
+    }
+}
+
+fn load_pool(dir: &Path) -> Result<DescriptorPool> {
+    let mut pool = DescriptorPool::new();
+    if !dir.exists() {
+        std::fs::create_dir_all(dir)?;
+        return Ok(pool);
+    }
+    let mut protos = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("proto") => protos.push(path),
+            Some("desc") | Some("pb") | Some("bin") => {
+                pool.decode_file_descriptor_set(std::fs::read(&path)?.as_slice())?
+            }
+            _ => {}
+        }
+    }
+    if !protos.is_empty() {
+        pool.add_file_descriptor_set(protox::compile(&protos, [dir])?)?;
+    }
+    Ok(pool)
+}
+
+fn message(name: &str) -> Result<MessageDescriptor> {
+    if name.is_empty() {
+        bail!("protobuf message not be empty");
+    }
+    POOL.read()
+        .map_err(|_| anyhow!("protobuf descriptors is poisoned"))?
+        .get_message_by_name(name)
+        .ok_or_else(|| anyhow!("could not find protobuf message: {}", name))
+}
+
+pub fn decode_to_json(name: &str, data: &[u8]) -> Result<String> {
+    let msg = DynamicMessage::decode(message(name)?, data)?;
+    Ok(serde_json::to_string_pretty(&msg)?)
+}
+
+pub fn encode_from_json(name: &str, json: &str) -> Result<Bytes> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let msg = DynamicMessage::deserialize(message(name)?, &mut deserializer)?;
+    deserializer.end()?;
+    Ok(Bytes::from(msg.encode_to_vec()))
+}
@@ -0,0 +1,155 @@
+//! 回放导出的json lines记录
+use crate::data::common::{PayloadTy, QoS};
+use crate::data::AString;
+use crate::util::export::ExportRow;
+use anyhow::{anyhow, bail, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 待回放的一条消息
+#[derive(Debug, Clone)]
+pub struct ReplayMsg {
+    pub topic: AString,
+    pub payload: Bytes,
+    pub payload_ty: PayloadTy,
+    pub proto_msg: AString,
+    pub qos: QoS,
+    pub retain: bool,
+    /// 毫秒
+    pub timestamp: i64,
+}
+
+impl TryFrom<ExportRow> for ReplayMsg {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ExportRow) -> Result<Self> {
+        let payload = match row.payload_encoding.as_str() {
+            "base64" => STANDARD
+                .decode(row.payload.as_bytes())
+                .map_err(|e| anyhow!("invalid base64: {}", e))?,
+            _ => row.payload.into_bytes(),
+        };
+        let qos = match row.qos.as_str() {
+            "0" => QoS::AtMostOnce,
+            "1" => QoS::AtLeastOnce,
+            "2" => QoS::ExactlyOnce,
+            qos => bail!("invalid qos: {}", qos),
+        };
+        Ok(Self {
+            topic: Arc::new(row.topic),
+            payload: Bytes::from(payload),
+            payload_ty: serde_json::from_value(Value::String(row.payload_ty)).unwrap_or_default(),
+            proto_msg: Arc::new(row.proto_msg),
+            qos,
+            retain: row.retain,
+            timestamp: row.timestamp,
+        })
+    }
+}
+
+/// 读取json lines记录，忽略空行
+pub fn read_capture(path: &Path) -> Result<Vec<ReplayMsg>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut msgs = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row: ExportRow = serde_json::from_str(line.as_str())
+            .map_err(|e| anyhow!("line {}: {}", index + 1, e))?;
+        msgs.push(ReplayMsg::try_from(row).map_err(|e| anyhow!("line {}: {}", index + 1, e))?);
+    }
+    Ok(msgs)
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    pub path: PathBuf,
+    /// 倍速，None表示不等待、尽快发送
+    pub speed: Option<f64>,
+    /// topic前缀替换(原前缀, 新前缀)
+    pub prefix: Option<(String, String)>,
+    pub looped: bool,
+}
+
+impl ReplayConfig {
+    /// 与上一条消息的间隔，按倍速缩放
+    pub fn delay(&self, last: Option<i64>, timestamp: i64) -> Option<Duration> {
+        let speed = self.speed?;
+        let interval = timestamp - last?;
+        if interval <= 0 {
+            return None;
+        }
+        Duration::try_from_secs_f64(interval as f64 / 1000.0 / speed).ok()
+    }
+
+    pub fn rewrite(&self, mut msg: ReplayMsg) -> ReplayMsg {
+        if let Some((from, to)) = &self.prefix {
+            if let Some(rest) = msg.topic.strip_prefix(from.as_str()) {
+                msg.topic = Arc::new(format!("{}{}", to, rest));
+            }
+        }
+        msg
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::db::{MsgDB, MsgTy};
+
+    #[test]
+    fn test_replay() {
+        let row: ExportRow = serde_json::from_str(
+            r#"{"topic":"abc/1","direction":"public","qos":"1","retain":false,"timestamp":1000,"payload_ty":"Hex","payload_encoding":"base64","payload":"AAE="}"#,
+        )
+        .unwrap();
+        let msg = ReplayMsg::try_from(row).unwrap();
+        assert_eq!(msg.payload.as_ref(), &[0u8, 1]);
+        assert_eq!(msg.qos, QoS::AtLeastOnce);
+        assert_eq!(msg.payload_ty, PayloadTy::Hex);
+        assert!(msg.proto_msg.is_empty());
+
+        let record = MsgDB {
+            ty: MsgTy::Subscribe,
+            topic: Arc::new("abc/2".to_string()),
+            payload: vec![0xff, 0x01],
+            payload_ty: PayloadTy::Protobuf,
+            proto_msg: Arc::new("demo.Sensor".to_string()),
+            qos: QoS::ExactlyOnce,
+            retain: true,
+            timestamp: 2000,
+            properties: Default::default(),
+        };
+        let row = serde_json::to_string(&ExportRow::from(&record)).unwrap();
+        let replayed =
+            ReplayMsg::try_from(serde_json::from_str::<ExportRow>(&row).unwrap()).unwrap();
+        assert_eq!(replayed.payload.as_ref(), record.payload.as_slice());
+        assert_eq!(replayed.payload_ty, PayloadTy::Protobuf);
+        assert_eq!(replayed.proto_msg, record.proto_msg);
+        assert_eq!(replayed.qos, QoS::ExactlyOnce);
+
+        let mut config = ReplayConfig {
+            path: PathBuf::new(),
+            speed: Some(2.0),
+            prefix: Some(("abc/".to_string(), "def/".to_string())),
+            looped: false,
+        };
+        assert_eq!(config.delay(None, 1000), None);
+        assert_eq!(
+            config.delay(Some(0), 1000),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(config.rewrite(msg).topic.as_str(), "def/1");
+        config.speed = None;
+        assert_eq!(config.delay(Some(0), 1000), None);
+    }
+}
@@ -0,0 +1,88 @@
+//! 发布模板中的变量替换：{{timestamp}}、{{uuid}}、{{counter}}、{{random_int(a,b)}}、{{env.NAME}}
+//! 其他{{...}}不是变量，原样保留
+use crate::util::now_timestamp;
+use anyhow::{anyhow, bail, Result};
+use lazy_static::lazy_static;
+use rand::Rng;
+use regex::Regex;
+
+lazy_static! {
+    static ref VARIABLE: Regex = Regex::new(r"\{\{\s*([^{}]*?)\s*\}\}").unwrap();
+    static ref RANDOM_INT: Regex =
+        Regex::new(r"^random_int\(\s*(-?\d+)\s*,\s*(-?\d+)\s*\)$").unwrap();
+}
+
+/// 替换text中的变量，counter为当前的发布计数
+pub fn expand(text: &str, counter: u64) -> Result<String> {
+    let mut rs = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in VARIABLE.captures_iter(text) {
+        let (Some(all), Some(name)) = (caps.get(0), caps.get(1)) else {
+            continue;
+        };
+        rs.push_str(&text[last..all.start()]);
+        match variable(name.as_str(), counter)? {
+            Some(val) => rs.push_str(val.as_str()),
+            None => rs.push_str(all.as_str()),
+        }
+        last = all.end();
+    }
+    rs.push_str(&text[last..]);
+    Ok(rs)
+}
+
+/// 不是变量时返回None；变量格式错误时返回错误
+fn variable(name: &str, counter: u64) -> Result<Option<String>> {
+    Ok(Some(match name {
+        "timestamp" => now_timestamp().to_string(),
+        "uuid" => uuid::Uuid::new_v4().to_string(),
+        "counter" => counter.to_string(),
+        _ => {
+            if let Some(key) = name.strip_prefix("env.") {
+                std::env::var(key).map_err(|_| anyhow!("env var not found: {}", key))?
+            } else if name.starts_with("random_int(") {
+                let Some(caps) = RANDOM_INT.captures(name) else {
+                    bail!("invalid {}", name);
+                };
+                let min: i64 = caps[1].parse()?;
+                let max: i64 = caps[2].parse()?;
+                if min > max {
+                    bail!("invalid random_int({},{})", min, max);
+                }
+                rand::thread_rng().gen_range(min..=max).to_string()
+            } else {
+                return Ok(None);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand("abc", 1).unwrap(), "abc");
+        assert_eq!(
+            expand("{\"id\": {{counter}}, \"n\": {{ counter }}}", 3).unwrap(),
+            "{\"id\": 3, \"n\": 3}"
+        );
+        let val: i64 = expand("{{random_int(-2, 2)}}", 0).unwrap().parse().unwrap();
+        assert!((-2..=2).contains(&val));
+        assert_eq!(expand("{{uuid}}", 0).unwrap().len(), 36);
+        assert!(expand("{{timestamp}}", 0).unwrap().parse::<i64>().is_ok());
+        std::env::set_var("FOR_MQTT_TEMPLATE_TEST", "abc");
+        assert_eq!(
+            expand("a/{{env.FOR_MQTT_TEMPLATE_TEST}}", 0).unwrap(),
+            "a/abc"
+        );
+        assert_eq!(
+            expand("{{unknown}}/{{ counter }}", 1).unwrap(),
+            "{{unknown}}/1"
+        );
+        assert!(expand("{{random_int(2,1)}}", 0).is_err());
+        assert!(expand("{{random_int(1)}}", 0).is_err());
+        assert!(expand("{{env.FOR_MQTT_TEMPLATE_MISSING}}", 0).is_err());
+    }
+}